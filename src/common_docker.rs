@@ -1,244 +1,1591 @@
-use log::{error, info};
+use bollard::{
+    container::{
+        Config, CreateContainerOptions, ListContainersOptions, LogOutput, LogsOptions,
+        NetworkingConfig, RemoveContainerOptions, StartContainerOptions, StopContainerOptions,
+    },
+    errors::Error as DockerError,
+    image::CreateImageOptions,
+    models::{
+        ContainerInspectResponse, ContainerStateStatusEnum, EndpointSettings, HealthConfig,
+        HealthStatusEnum, HostConfig, Mount, MountTypeEnum, PortBinding,
+        RestartPolicy as DockerRestartPolicy, RestartPolicyNameEnum,
+    },
+    network::{ConnectNetworkOptions, CreateNetworkOptions, ListNetworksOptions},
+    volume::CreateVolumeOptions,
+    Docker,
+};
+use eyre::{Context, Result};
+use futures_util::TryStreamExt;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
 use std::{
-    collections::HashMap,
-    io::Write,
-    process::{self, Command},
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    config::og_dir,
+    container_runtime,
+    duration::{format_duration, parse_compose_duration, parse_timestamp},
+    error::{ErrorKind, OgError},
+    ports::{self, PortRecord},
 };
-use tempfile::NamedTempFile;
 
+/// Labels `docker compose` sets, so it and `docker ps --filter` keep recognizing og's containers
+pub const PROJECT_LABEL: &str = "com.docker.compose.project";
+pub const SERVICE_LABEL: &str = "com.docker.compose.service";
+const CONTAINER_NUMBER_LABEL: &str = "com.docker.compose.container-number";
+const ONEOFF_LABEL: &str = "com.docker.compose.oneoff";
+const CONFIG_HASH_LABEL: &str = "com.docker.compose.config-hash";
+const NETWORK_LABEL: &str = "com.docker.compose.network";
+const VOLUME_LABEL: &str = "com.docker.compose.volume";
+/// Network services join when they don't list any
+const DEFAULT_NETWORK: &str = "default";
+/// How long `start` waits for dependencies to become healthy
+const DEPENDENCY_TIMEOUT: Duration = Duration::from_secs(300);
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A compose project og runs through the Docker API, its file is kept so `docker compose` can be run against it by hand
 #[derive(Debug)]
 pub struct DockerCompose {
-    compose_file: NamedTempFile,
+    project: String,
+    compose_file: PathBuf,
+    file: ComposeFile,
+    ports: PortRecord,
+    /// Set when ports must be published as given, the hint tells how to resolve a conflict
+    fixed_ports: Option<String>,
+    /// Values for `${NAME}` placeholders, kept out of the written file
+    variables: BTreeMap<String, String>,
 }
 
 impl DockerCompose {
-    pub fn new(compose_string: String) -> DockerCompose {
-        let mut file = NamedTempFile::new().unwrap_or_else(|err| {
-            error!("Could not create a temp file{}", err);
-            process::exit(1)
-        });
-        write!(file, "{}", compose_string).unwrap_or_else(|err| {
-            error!("{err}");
-            process::exit(1)
-        });
+    /// Writes the file to `~/.og-cli/compose/<project>.yaml`
+    pub fn new(project: &str, file: &ComposeFile) -> Result<DockerCompose> {
+        Self::new_in(&og_dir()?.join("compose"), project, file, None)
+    }
 
-        DockerCompose { compose_file: file }
+    /// Never moves a port to a free one, for services that announce their own address like replica set members
+    pub fn with_fixed_ports(
+        project: &str,
+        file: &ComposeFile,
+        hint: &str,
+    ) -> Result<DockerCompose> {
+        Self::new_in(
+            &og_dir()?.join("compose"),
+            project,
+            file,
+            Some(hint.to_string()),
+        )
     }
 
-    pub fn start(&self) {
-        let command = Command::new("docker")
-            .args([
-                "compose",
-                "-p",
-                "test",
-                "-f",
-                (self.compose_file.path().to_str().unwrap()),
-                "up",
-                "-d",
-            ])
-            .output()
-            .expect("Failed to run docker compose");
+    fn new_in(
+        dir: &Path,
+        project: &str,
+        file: &ComposeFile,
+        fixed_ports: Option<String>,
+    ) -> Result<DockerCompose> {
+        fs::create_dir_all(dir).with_context(|| format!("Could not create {}", dir.display()))?;
+        let ports = PortRecord::load(&dir.join("ports.json"))?;
+        let mut file = file.clone();
+        if fixed_ports.is_none() {
+            for (service, definition) in &mut file.services {
+                for port in definition
+                    .ports
+                    .iter_mut()
+                    .filter(|p| p.protocol() == "tcp")
+                {
+                    port.published =
+                        ports.published(&port_key(project, service, port.target), port.published);
+                }
+            }
+        }
+
+        let compose = DockerCompose {
+            project: project.to_string(),
+            compose_file: dir.join(format!("{project}.yaml")),
+            file,
+            ports,
+            fixed_ports,
+            variables: BTreeMap::new(),
+        };
+        compose.write()?;
 
-        let stdout = String::from_utf8(command.stdout).unwrap();
-        dbg!(&self);
-        info!("{}", &stdout);
+        Ok(compose)
     }
 
-    pub fn stop(&self) {
-        let command = Command::new("docker")
-            .args([
-                "compose",
-                "-p",
-                "test",
-                "-f",
-                (self.compose_file.path().to_str().unwrap()),
-                "down",
-            ])
-            .output()
-            .expect("Failed to stop docker compose");
+    /// Fills `${NAME}` placeholders when containers are created, e.g. with secrets that mustn't be written to disk
+    pub fn with_variables(mut self, variables: BTreeMap<String, String>) -> Self {
+        self.variables = variables;
+        self
+    }
 
-        let stdout = String::from_utf8(command.stdout).unwrap();
-        info!("{}", &stdout);
+    fn write(&self) -> Result<()> {
+        fs::write(&self.compose_file, self.file.to_yaml()?)
+            .with_context(|| format!("Could not write {}", self.compose_file.display()))
     }
 
-    pub fn is_running() -> bool {
-        let command = Command::new("docker").args(["info"]).output();
+    pub fn compose_file(&self) -> &Path {
+        &self.compose_file
+    }
 
-        let stderr = String::from_utf8(command.unwrap().stderr).unwrap();
+    pub async fn start(&mut self) -> Result<()> {
+        let docker = container_runtime::connect()?;
+        self.up(&docker, Instant::now() + DEPENDENCY_TIMEOUT).await
+    }
 
-        if stderr.contains("Cannot connect to the Docker daemon") {
-            return false;
+    /// Starts the services and waits until their healthchecks pass
+    pub async fn start_and_wait(&mut self, timeout: Duration) -> Result<()> {
+        let docker = container_runtime::connect()?;
+        let deadline = Instant::now() + timeout;
+        self.up(&docker, deadline).await?;
+
+        for service in self.file.services.keys() {
+            self.wait_for(
+                &docker,
+                service,
+                DependencyCondition::ServiceHealthy,
+                deadline,
+            )
+            .await?;
         }
 
-        return true;
+        Ok(())
     }
-}
 
-#[derive(Debug)]
-pub struct DockerComposeBuilder {
-    services: Vec<Service>,
-}
+    pub async fn stop(&self) -> Result<()> {
+        self.down(false).await
+    }
 
-impl DockerComposeBuilder {
-    pub fn new() -> DockerComposeBuilder {
-        DockerComposeBuilder {
-            services: Vec::new(),
-        }
+    /// Stops the services and deletes their volumes
+    pub async fn stop_and_remove_volumes(&self) -> Result<()> {
+        self.down(true).await
     }
 
-    pub fn add_service<S: Into<String>>(
-        mut self,
-        name: S,
-        image: S,
-        command: Option<S>,
-        environment: Option<HashMap<String, String>>,
-        port_mapping: Option<HashMap<u32, u32>>,
-        volumes: Option<Vec<Volume>>,
-    ) -> DockerComposeBuilder {
-        self.services.push(Service {
-            name: name.into(),
-            image: image.into(),
-            command: command.map(|s| s.into()),
-            environment,
-            port_mapping,
-            volumes,
-        });
-        self
+    async fn up(&mut self, docker: &Docker, deadline: Instant) -> Result<()> {
+        self.preflight_ports(docker).await?;
+        for network in self.used_networks() {
+            self.ensure_network(docker, &network).await?;
+        }
+        for (key, volume) in &self.file.volumes {
+            self.ensure_volume(docker, key, volume).await?;
+        }
+
+        for service in start_order(&self.file)? {
+            for (dependency, condition) in &self.file.services[service].depends_on {
+                self.wait_for(docker, dependency, condition.condition, deadline)
+                    .await?;
+            }
+            self.run_service(docker, service).await?;
+        }
+
+        Ok(())
     }
 
-    pub fn build(&self) -> DockerCompose {
-        let mut sb = String::new();
-        sb.push_str("services:\n");
-        for service in &self.services {
-            sb.push_str(&Self::build_service(service));
-            sb.push('\n');
-        }
-        sb.push_str("volumes:\n");
-        for service in &self.services {
-            let volumes = &service.volumes;
-            match &volumes {
-                Some(volumes) => {
-                    for volume in volumes.iter() {
-                        sb.push_str(&*indent(format!("{}:", volume.volume_name)));
+    /// Moves published ports something else holds, recording the new ones for later starts
+    async fn preflight_ports(&mut self, docker: &Docker) -> Result<()> {
+        let services: Vec<String> = self.file.services.keys().cloned().collect();
+        let mut moved = false;
+
+        for service in services {
+            let container = self.container_name(&service);
+            for index in 0..self.file.services[&service].ports.len() {
+                let port = &self.file.services[&service].ports[index];
+                let (target, current) = (port.target, port.published);
+                // Only TCP ports are probed and moved
+                if port.protocol() != "tcp" {
+                    continue;
+                }
+                if let Some(hint) = &self.fixed_ports {
+                    ports::check(docker, current, &container, hint).await?;
+                    continue;
+                }
+                let published = ports::choose(docker, current, &container).await?;
+                if published != current {
+                    self.ports.remap(
+                        &port_key(&self.project, &service, target),
+                        current,
+                        published,
+                    )?;
+                    if let Some(definition) = self.file.services.get_mut(&service) {
+                        definition.ports[index].published = published;
                     }
+                    moved = true;
                 }
-                None => (),
             }
         }
-        DockerCompose::new(sb)
-    }
 
-    pub fn build_string(&self) -> String {
-        let mut sb = String::new();
-        sb.push_str("service:\n");
-        for service in &self.services {
-            sb.push_str(&Self::build_service(service));
-            sb.push('\n');
+        if moved {
+            self.write()?;
         }
-        sb
+        Ok(())
     }
 
-    fn build_service(service: &Service) -> String {
-        let mut service_string = Vec::new();
-        for x in service.build() {
-            service_string.push(indent(x));
+    async fn down(&self, remove_volumes: bool) -> Result<()> {
+        let docker = container_runtime::connect()?;
+        let label = format!("{PROJECT_LABEL}={}", self.project);
+
+        let options = ListContainersOptions {
+            all: true,
+            filters: HashMap::from([("label", vec![label.as_str()])]),
+            ..Default::default()
+        };
+        for container in docker.list_containers(Some(options)).await? {
+            let Some(id) = container.id else { continue };
+            let name = container
+                .names
+                .and_then(|n| n.first().map(|n| n.trim_start_matches('/').to_string()))
+                .unwrap_or_else(|| id.clone());
+            info!("Removing container {name}");
+            docker
+                .stop_container(&id, None::<StopContainerOptions>)
+                .await
+                .or_else(ignore_not_modified)?;
+            docker
+                .remove_container(&id, None::<RemoveContainerOptions>)
+                .await?;
+        }
+
+        let options = ListNetworksOptions {
+            filters: HashMap::from([("label", vec![label.as_str()])]),
+        };
+        for network in docker.list_networks(Some(options)).await? {
+            let Some(name) = network.name else { continue };
+            info!("Removing network {name}");
+            docker
+                .remove_network(&name)
+                .await
+                .or_else(ignore_not_found)?;
         }
-        service_string.join("\n")
+
+        if remove_volumes {
+            for (key, _) in self.file.volumes.iter().filter(|(_, v)| !v.external) {
+                let name = self.volume_name(key);
+                info!("Removing volume {name}");
+                docker
+                    .remove_volume(&name, None)
+                    .await
+                    .or_else(ignore_not_found)?;
+            }
+        }
+
+        Ok(())
     }
-}
 
-#[derive(Debug)]
-pub struct Service {
-    name: String,
-    image: String,
-    command: Option<String>,
-    environment: Option<HashMap<String, String>>,
-    port_mapping: Option<HashMap<u32, u32>>,
-    volumes: Option<Vec<Volume>>,
-}
+    /// Creates the container unless one with the same configuration exists, then starts it
+    async fn run_service(&self, docker: &Docker, service: &str) -> Result<()> {
+        let name = self.container_name(service);
+        let config = self.container_config(service)?;
+        let hash = config
+            .labels
+            .as_ref()
+            .and_then(|l| l.get(CONFIG_HASH_LABEL));
 
-#[derive(Debug)]
-pub struct Volume {
-    pub volume_name: String,
-    pub volume_type: String,
-    pub bind: String,
-    pub mode: String,
-}
-
-impl Service {
-    pub fn build(&self) -> Vec<String> {
-        let mut service_vec = Vec::new();
-        service_vec.push(format!("{}:", self.name));
-        service_vec.push(indent(format!("image: {}", self.image)));
-        match &self.command {
-            Some(command) => service_vec.push(indent(format!("command: {}", command))),
-            None => (),
-        }
-
-        match &self.environment {
-            Some(environments) => {
-                service_vec.push(indent(String::from("environment:")));
-                for (key, value) in environments.iter() {
-                    service_vec.push(indent(indent(format!("- {}={}", key, value))));
+        // Pulled before an outdated container is removed, so a failed pull leaves it in place
+        let image = &self.file.services[service].image;
+        if docker.inspect_image(image).await.is_err() {
+            pull_image(docker, image).await?;
+        }
+
+        match docker.inspect_container(&name, None).await {
+            Ok(existing) if config_hash(&existing) == hash => {
+                if is_running(&existing) {
+                    debug!("Container {name} is already running");
+                } else {
+                    info!("Starting container {name}");
+                    docker
+                        .start_container(&name, None::<StartContainerOptions<String>>)
+                        .await?;
                 }
+                return Ok(());
             }
-            None => (),
+            Ok(_) => {
+                info!("Recreating container {name}, its configuration changed");
+                docker
+                    .remove_container(
+                        &name,
+                        Some(RemoveContainerOptions {
+                            force: true,
+                            ..Default::default()
+                        }),
+                    )
+                    .await?;
+            }
+            Err(DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            }) => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        info!("Creating container {name}");
+        docker
+            .create_container(
+                Some(CreateContainerOptions {
+                    name: name.as_str(),
+                    platform: None,
+                }),
+                config,
+            )
+            .await?;
+        for network in self.service_networks(service).iter().skip(1) {
+            docker
+                .connect_network(
+                    &self.network_name(network),
+                    ConnectNetworkOptions {
+                        container: name.as_str(),
+                        endpoint_config: endpoint(service),
+                    },
+                )
+                .await?;
         }
+        docker
+            .start_container(&name, None::<StartContainerOptions<String>>)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Polls the service's container until `condition` holds, failing once it can't anymore
+    async fn wait_for(
+        &self,
+        docker: &Docker,
+        service: &str,
+        condition: DependencyCondition,
+        deadline: Instant,
+    ) -> Result<()> {
+        let name = self.container_name(service);
+        loop {
+            let container = docker.inspect_container(&name, None).await?;
+            let state = container.state.unwrap_or_default();
+            let health = state.health.and_then(|h| h.status);
+            let exit_code = state.exit_code.unwrap_or_default();
+            let running = state.status == Some(ContainerStateStatusEnum::RUNNING);
+            let stopped = matches!(
+                state.status,
+                Some(ContainerStateStatusEnum::EXITED | ContainerStateStatusEnum::DEAD)
+            );
 
-        match &self.port_mapping {
-            Some(ports) => {
-                service_vec.push(indent(String::from("ports:")));
-                for (source, destination) in ports.iter() {
-                    service_vec.push(indent(indent(format!("- \"{}:{}\"", source, destination))));
+            let done = match condition {
+                DependencyCondition::ServiceStarted => running,
+                DependencyCondition::ServiceHealthy => match health {
+                    Some(HealthStatusEnum::HEALTHY) => true,
+                    Some(HealthStatusEnum::UNHEALTHY) => Err(OgError::msg(
+                        ErrorKind::Docker,
+                        format!("{name} is unhealthy, check its logs with: docker logs {name}"),
+                    ))?,
+                    Some(HealthStatusEnum::STARTING) => false,
+                    _ => running,
+                },
+                DependencyCondition::ServiceCompletedSuccessfully if stopped => {
+                    if exit_code != 0 {
+                        Err(OgError::msg(
+                            ErrorKind::Docker,
+                            format!("{name} exited with code {exit_code}"),
+                        ))?;
+                    }
+                    true
                 }
+                DependencyCondition::ServiceCompletedSuccessfully => false,
+            };
+            if done {
+                return Ok(());
+            }
+            if stopped && condition != DependencyCondition::ServiceCompletedSuccessfully {
+                Err(OgError::msg(
+                    ErrorKind::Docker,
+                    format!(
+                        "{name} stopped while starting, check its logs with: docker logs {name}"
+                    ),
+                ))?;
+            }
+            if Instant::now() >= deadline {
+                Err(OgError::msg(
+                    ErrorKind::Docker,
+                    format!("{name} is not ready yet, check its logs with: docker logs {name}"),
+                ))?;
             }
-            None => (),
+
+            tokio::time::sleep(POLL_INTERVAL).await;
         }
+    }
 
-        match &self.volumes {
-            Some(volumes) => {
-                service_vec.push(indent(String::from("volumes:")));
-                for volume in volumes.iter() {
-                    service_vec.push(indent(format!(
-                        "- {}:{}:{}",
-                        volume.volume_name, volume.bind, volume.mode
-                    )))
-                }
+    async fn ensure_network(&self, docker: &Docker, key: &str) -> Result<()> {
+        let network = self.file.networks.get(key).cloned().unwrap_or_default();
+        let name = self.network_name(key);
+
+        match docker.inspect_network::<String>(&name, None).await {
+            Ok(_) => return Ok(()),
+            Err(DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            }) if network.external => Err(OgError::msg(
+                ErrorKind::Config,
+                format!("External network {name} doesn't exist"),
+            ))?,
+            Err(DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            }) => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        info!("Creating network {name}");
+        docker
+            .create_network(CreateNetworkOptions {
+                name: name.clone(),
+                check_duplicate: true,
+                driver: network.driver.unwrap_or_else(|| "bridge".into()),
+                labels: HashMap::from([
+                    (PROJECT_LABEL.to_string(), self.project.clone()),
+                    (NETWORK_LABEL.to_string(), key.to_string()),
+                ]),
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    async fn ensure_volume(&self, docker: &Docker, key: &str, volume: &NamedVolume) -> Result<()> {
+        let name = self.volume_name(key);
+
+        match docker.inspect_volume(&name).await {
+            Ok(_) => return Ok(()),
+            Err(DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            }) if volume.external => Err(OgError::msg(
+                ErrorKind::Config,
+                format!("External volume {name} doesn't exist"),
+            ))?,
+            Err(DockerError::DockerResponseServerError {
+                status_code: 404, ..
+            }) => {}
+            Err(error) => return Err(error.into()),
+        }
+
+        info!("Creating volume {name}");
+        let mut labels: HashMap<String, String> = volume.labels.clone().into_iter().collect();
+        labels.insert(PROJECT_LABEL.into(), self.project.clone());
+        labels.insert(VOLUME_LABEL.into(), key.into());
+        docker
+            .create_volume(CreateVolumeOptions {
+                name,
+                labels,
+                ..Default::default()
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// The container the Docker API creates for a service, including compose's labels
+    fn container_config(&self, service: &str) -> Result<Config<String>> {
+        let definition = &self.file.services[service];
+
+        let mut port_bindings: HashMap<String, Option<Vec<PortBinding>>> = HashMap::new();
+        for port in &definition.ports {
+            port_bindings
+                .entry(format!("{}/{}", port.target, port.protocol()))
+                .or_default()
+                .get_or_insert_with(Vec::new)
+                .push(PortBinding {
+                    host_ip: port.host_ip.clone(),
+                    host_port: Some(port.published.to_string()),
+                });
+        }
+
+        let mounts = definition
+            .volumes
+            .iter()
+            .map(|volume| Mount {
+                target: Some(volume.target.clone()),
+                source: match volume.mount_type {
+                    MountType::Volume => Some(self.volume_name(&volume.source)),
+                    MountType::Bind => Some(volume.source.clone()),
+                    MountType::Tmpfs => None,
+                },
+                typ: Some(match volume.mount_type {
+                    MountType::Volume => MountTypeEnum::VOLUME,
+                    MountType::Bind => MountTypeEnum::BIND,
+                    MountType::Tmpfs => MountTypeEnum::TMPFS,
+                }),
+                read_only: Some(volume.read_only),
+                ..Default::default()
+            })
+            .collect();
+
+        let networks = self.service_networks(service);
+        let network_mode = self.network_name(&networks[0]);
+
+        let mut labels: HashMap<String, String> = definition.labels.clone().into_iter().collect();
+        labels.extend([
+            (PROJECT_LABEL.to_string(), self.project.clone()),
+            (SERVICE_LABEL.to_string(), service.to_string()),
+            (CONTAINER_NUMBER_LABEL.to_string(), "1".to_string()),
+            (ONEOFF_LABEL.to_string(), "False".to_string()),
+            (
+                CONFIG_HASH_LABEL.to_string(),
+                definition_hash(&self.project, definition)?,
+            ),
+        ]);
+
+        Ok(Config {
+            image: Some(definition.image.clone()),
+            cmd: definition
+                .command
+                .as_deref()
+                .map(|c| split_command(&interpolate(c, &self.variables))),
+            env: Some(
+                definition
+                    .environment
+                    .iter()
+                    .map(|(k, v)| format!("{k}={}", interpolate(v, &self.variables)))
+                    .collect(),
+            ),
+            exposed_ports: Some(
+                port_bindings
+                    .keys()
+                    .map(|p| (p.clone(), HashMap::new()))
+                    .collect(),
+            ),
+            healthcheck: definition
+                .healthcheck
+                .as_ref()
+                .map(|h| health_config(h, &self.variables))
+                .transpose()?,
+            labels: Some(labels),
+            host_config: Some(HostConfig {
+                port_bindings: Some(port_bindings),
+                mounts: Some(mounts),
+                restart_policy: definition.restart.map(restart_policy),
+                network_mode: Some(network_mode.clone()),
+                ..Default::default()
+            }),
+            networking_config: Some(NetworkingConfig {
+                endpoints_config: HashMap::from([(network_mode, endpoint(service))]),
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// The host port `target` is published on, including a port picked earlier instead of a taken one
+    pub fn published_port(&self, service: &str, target: u16) -> Option<u16> {
+        self.file
+            .services
+            .get(service)?
+            .ports
+            .iter()
+            .find(|p| p.target == target && p.protocol() == "tcp")
+            .map(|p| p.published)
+    }
+
+    /// The service's `container_name`, or the name compose would give it
+    pub fn container_name(&self, service: &str) -> String {
+        self.file
+            .services
+            .get(service)
+            .and_then(|s| s.container_name.clone())
+            .unwrap_or_else(|| container_name(&self.project, service))
+    }
+
+    fn volume_name(&self, key: &str) -> String {
+        self.file
+            .volumes
+            .get(key)
+            .and_then(|v| v.name.clone())
+            .unwrap_or_else(|| format!("{}_{key}", self.project))
+    }
+
+    fn network_name(&self, key: &str) -> String {
+        self.file
+            .networks
+            .get(key)
+            .and_then(|n| n.name.clone())
+            .unwrap_or_else(|| format!("{}_{key}", self.project))
+    }
+
+    fn service_networks(&self, service: &str) -> Vec<String> {
+        match &self.file.services[service].networks {
+            networks if networks.is_empty() => vec![DEFAULT_NETWORK.to_string()],
+            networks => networks.clone(),
+        }
+    }
+
+    fn used_networks(&self) -> Vec<String> {
+        let mut networks: Vec<String> = self
+            .file
+            .services
+            .keys()
+            .flat_map(|s| self.service_networks(s))
+            .collect();
+        networks.sort();
+        networks.dedup();
+        networks
+    }
+}
+
+/// `<project>-<service>-1` like compose names containers
+pub fn container_name(project: &str, service: &str) -> String {
+    format!("{project}-{service}-1")
+}
+
+/// Splits a command string like compose does, quotes group words as in a shell
+fn split_command(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None | Some('"'), '\\') => {
+                word.get_or_insert_with(String::new).extend(chars.next());
             }
-            None => (),
+            (_, c) => word.get_or_insert_with(String::new).push(c),
         }
-        service_vec
     }
+    words.extend(word);
+
+    words
+}
+
+/// Identifies a published port in the port record, e.g. `og-mongodb/mongodb-local/27017`
+fn port_key(project: &str, service: &str, target: u16) -> String {
+    format!("{project}/{service}/{target}")
 }
 
-fn indent(string: String) -> String {
-    format!("  {string}")
+/// Services ordered so each comes after the ones it depends on
+fn start_order(file: &ComposeFile) -> Result<Vec<&str>> {
+    let mut order: Vec<&str> = Vec::new();
+    while order.len() < file.services.len() {
+        let ready: Vec<&str> = file
+            .services
+            .iter()
+            .filter(|(name, _)| !order.contains(&name.as_str()))
+            .filter(|(_, service)| {
+                service
+                    .depends_on
+                    .keys()
+                    .all(|d| order.contains(&d.as_str()))
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        if ready.is_empty() {
+            let blocked: Vec<&str> = file
+                .services
+                .keys()
+                .map(String::as_str)
+                .filter(|s| !order.contains(s))
+                .collect();
+            Err(OgError::msg(
+                ErrorKind::Config,
+                format!(
+                    "The dependencies of {} are missing or circular",
+                    blocked.join(", ")
+                ),
+            ))?;
+        }
+        order.extend(ready);
+    }
+
+    Ok(order)
+}
+
+/// Compose replaces `$$` with `$` and `${NAME}` with a variable, falling back to the environment like compose does
+fn interpolate(value: &str, variables: &BTreeMap<String, String>) -> String {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(index) = rest.find('$') {
+        result.push_str(&rest[..index]);
+        rest = &rest[index + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+        } else if let Some((name, after)) = rest.strip_prefix('{').and_then(|r| r.split_once('}')) {
+            let value = variables.get(name).cloned().or_else(|| env::var(name).ok());
+            result.push_str(&value.unwrap_or_default());
+            rest = after;
+        } else {
+            result.push('$');
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn health_config(
+    healthcheck: &Healthcheck,
+    variables: &BTreeMap<String, String>,
+) -> Result<HealthConfig> {
+    let nanos = |value: &Option<String>| -> Result<Option<i64>> {
+        value
+            .as_deref()
+            .map(|v| {
+                parse_compose_duration(v)
+                    .map(|d| d.as_nanos() as i64)
+                    .map_err(|e| OgError::msg(ErrorKind::Config, e).into())
+            })
+            .transpose()
+    };
+
+    Ok(HealthConfig {
+        test: Some(
+            healthcheck
+                .test
+                .iter()
+                .map(|t| interpolate(t, variables))
+                .collect(),
+        ),
+        interval: nanos(&healthcheck.interval)?,
+        timeout: nanos(&healthcheck.timeout)?,
+        retries: healthcheck.retries.map(i64::from),
+        start_period: nanos(&healthcheck.start_period)?,
+        start_interval: None,
+    })
+}
+
+fn restart_policy(policy: RestartPolicy) -> DockerRestartPolicy {
+    DockerRestartPolicy {
+        name: Some(match policy {
+            RestartPolicy::No => RestartPolicyNameEnum::NO,
+            RestartPolicy::Always => RestartPolicyNameEnum::ALWAYS,
+            RestartPolicy::OnFailure => RestartPolicyNameEnum::ON_FAILURE,
+            RestartPolicy::UnlessStopped => RestartPolicyNameEnum::UNLESS_STOPPED,
+        }),
+        maximum_retry_count: None,
+    }
+}
+
+/// Other services reach the container by its service name
+fn endpoint(service: &str) -> EndpointSettings {
+    EndpointSettings {
+        aliases: Some(vec![service.to_string()]),
+        ..Default::default()
+    }
+}
+
+fn is_running(container: &ContainerInspectResponse) -> bool {
+    container.state.as_ref().and_then(|s| s.status) == Some(ContainerStateStatusEnum::RUNNING)
+}
+
+fn config_hash(container: &ContainerInspectResponse) -> Option<&String> {
+    container
+        .config
+        .as_ref()
+        .and_then(|c| c.labels.as_ref())
+        .and_then(|l| l.get(CONFIG_HASH_LABEL))
+}
+
+/// Changes whenever the service's definition does, so its container has to be recreated
+fn definition_hash(project: &str, service: &Service) -> Result<String> {
+    let definition = serde_yaml::to_string(service)?;
+    Ok(Sha256::digest(format!("{project}\n{definition}"))
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Stopping an already stopped container answers 304
+fn ignore_not_modified(error: DockerError) -> Result<(), DockerError> {
+    match error {
+        DockerError::DockerResponseServerError {
+            status_code: 304, ..
+        } => Ok(()),
+        error => ignore_not_found(error),
+    }
+}
+
+fn ignore_not_found(error: DockerError) -> Result<(), DockerError> {
+    match error {
+        DockerError::DockerResponseServerError {
+            status_code: 404, ..
+        } => Ok(()),
+        error => Err(error),
+    }
+}
+
+/// Pulls an image, logging the progress of its layers
+pub async fn pull_image(docker: &Docker, image: &str) -> Result<()> {
+    let (image, tag) = split_image(image);
+    let image_options = Some(CreateImageOptions {
+        from_image: image,
+        tag,
+        ..Default::default()
+    });
+
+    let mut stream = docker.create_image(image_options, None, None);
+
+    while let Some(output) = stream.try_next().await? {
+        if output.error.is_some() {
+            error!("{}", output.error.unwrap_or_else(|| "".to_string()));
+        } else {
+            info!(
+                "{} {}",
+                output.status.unwrap_or_else(|| "".to_string()),
+                output.progress.unwrap_or_else(|| "".to_string())
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Splits an image reference into repository and tag, registry ports are not mistaken for tags
+pub fn split_image(image: &str) -> (&str, &str) {
+    match image.rsplit_once(':') {
+        Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+        _ => (image, "latest"),
+    }
+}
+
+/// Name of the compose project of an og service, e.g. `og-mongodb`
+pub fn project_name(service: &str) -> String {
+    format!("og-{service}")
+}
+
+/// A compose file, fields left empty are omitted so the output stays close to a hand-written one
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComposeFile {
+    pub services: BTreeMap<String, Service>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub volumes: BTreeMap<String, NamedVolume>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub networks: BTreeMap<String, Network>,
+}
+
+impl ComposeFile {
+    pub fn to_yaml(&self) -> Result<String> {
+        serde_yaml::to_string(self).context("Could not serialize the compose file")
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<ComposeFile> {
+        serde_yaml::from_str(yaml).context("Invalid compose file")
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Service {
+    pub image: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<PortMapping>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<VolumeMount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub healthcheck: Option<Healthcheck>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub networks: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub depends_on: BTreeMap<String, Dependency>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restart: Option<RestartPolicy>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+/// Port in the long syntax, `published` is the host port
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PortMapping {
+    pub target: u16,
+    pub published: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_ip: Option<String>,
+    /// `tcp` or `udp`, defaults to `tcp`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub protocol: Option<String>,
+}
+
+impl PortMapping {
+    pub fn protocol(&self) -> &str {
+        self.protocol.as_deref().unwrap_or("tcp")
+    }
+}
+
+/// Mount in the long syntax, `source` is a named volume or a host path depending on the type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VolumeMount {
+    #[serde(rename = "type")]
+    pub mount_type: MountType,
+    pub source: String,
+    pub target: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+}
+
+impl VolumeMount {
+    pub fn volume<S: Into<String>>(source: S, target: S) -> VolumeMount {
+        VolumeMount {
+            mount_type: MountType::Volume,
+            source: source.into(),
+            target: target.into(),
+            read_only: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MountType {
+    Volume,
+    Bind,
+    Tmpfs,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Healthcheck {
+    /// e.g. `["CMD", "mongosh", "--eval", "db.adminCommand('ping')"]`
+    pub test: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_period: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Dependency {
+    pub condition: DependencyCondition,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+    ServiceStarted,
+    ServiceHealthy,
+    ServiceCompletedSuccessfully,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    No,
+    Always,
+    OnFailure,
+    UnlessStopped,
+}
+
+/// Top-level volume, `name` pins the docker volume name instead of prefixing it with the project
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NamedVolume {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub external: bool,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Network {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub external: bool,
+}
+
+/// Prints a container's logs, stderr to stderr
+pub async fn logs(
+    docker: &Docker,
+    container: &str,
+    follow: bool,
+    since: Option<Duration>,
+    tail: Option<usize>,
+) -> Result<()> {
+    // Durations reaching back before the epoch mean all logs
+    let since = since
+        .and_then(|since| SystemTime::now().checked_sub(since))
+        .and_then(|start| start.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |start| start.as_secs() as i64);
+    let options = LogsOptions {
+        follow,
+        stdout: true,
+        stderr: true,
+        since,
+        tail: tail
+            .map(|t| t.to_string())
+            .unwrap_or_else(|| "all".to_string()),
+        ..Default::default()
+    };
+
+    let mut stream = docker.logs(container, Some(options));
+    while let Some(chunk) = stream.try_next().await? {
+        match chunk {
+            LogOutput::StdErr { message } => io::stderr().write_all(&message)?,
+            chunk => io::stdout().write_all(&chunk.into_bytes())?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Status fields `og <service> status` prints for a container, from its state to the size of its data volume
+pub async fn container_fields(
+    docker: &Docker,
+    inspect: &ContainerInspectResponse,
+    container_port: u16,
+    data_dir: &str,
+) -> Result<Vec<(&'static str, Value)>> {
+    let state = inspect.state.clone().unwrap_or_default();
+    let running = state.running.unwrap_or_default();
+    let started_at = state.started_at.as_deref().and_then(parse_timestamp);
+    let uptime = started_at
+        .filter(|_| running)
+        .and_then(|s| SystemTime::now().duration_since(s).ok())
+        .map(format_duration);
+    let health = state
+        .health
+        .and_then(|h| h.status)
+        .map(|h| h.to_string())
+        .unwrap_or_else(|| "none".to_string());
+
+    let image = inspect.config.as_ref().and_then(|c| c.image.clone());
+    let digest = match &inspect.image {
+        Some(id) => docker
+            .inspect_image(id)
+            .await
+            .ok()
+            .and_then(|i| i.repo_digests)
+            .and_then(|d| d.into_iter().next())
+            .or_else(|| Some(id.clone())),
+        None => None,
+    };
+
+    let port = host_port(inspect, container_port);
+    let volume = inspect
+        .mounts
+        .iter()
+        .flatten()
+        .find(|m| m.destination.as_deref() == Some(data_dir))
+        .and_then(|m| m.name.clone());
+    let volume_size = match &volume {
+        Some(volume) => volume_size(docker, volume).await?,
+        None => None,
+    };
+
+    Ok(vec![
+        (
+            "status",
+            Value::from(state.status.map(|s| s.to_string()).unwrap_or_default()),
+        ),
+        ("health", Value::from(health)),
+        ("uptime", Value::from(uptime)),
+        ("image", Value::from(image)),
+        ("digest", Value::from(digest)),
+        (
+            "ports",
+            Value::from(port.map(|p| format!("0.0.0.0:{p}->{container_port}/tcp"))),
+        ),
+        ("port", Value::from(port)),
+        ("volume", Value::from(volume)),
+        ("volume_size", Value::from(volume_size.map(format_size))),
+    ])
+}
+
+/// The host port `container_port` is published on, also known while the container is stopped
+pub fn host_port(inspect: &ContainerInspectResponse, container_port: u16) -> Option<u16> {
+    inspect
+        .host_config
+        .as_ref()?
+        .port_bindings
+        .as_ref()?
+        .get(&format!("{container_port}/tcp"))?
+        .as_ref()?
+        .iter()
+        .find_map(|b| b.host_port.as_ref()?.parse().ok())
+}
+
+/// Size of a volume in bytes, docker only calculates it on request
+pub async fn volume_size(docker: &Docker, volume: &str) -> Result<Option<u64>> {
+    Ok(docker
+        .df()
+        .await?
+        .volumes
+        .into_iter()
+        .flatten()
+        .find(|v| v.name == volume)
+        .and_then(|v| v.usage_data)
+        .and_then(|u| u64::try_from(u.size).ok()))
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn service(image: &str) -> Service {
+        Service {
+            image: image.into(),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn build_simple_service() {
-        let dockerbuilder =
-            DockerComposeBuilder::new().add_service("test", "testimage", None, None, None, None);
-        dbg!(dockerbuilder.build_string());
+        let file = ComposeFile {
+            services: BTreeMap::from([("test".into(), service("testimage"))]),
+            ..Default::default()
+        };
+
         assert_eq!(
-            dockerbuilder.build_string(),
-            "service:\n  test:\n    image: testimage\n"
+            file.to_yaml().unwrap(),
+            "services:\n  test:\n    image: testimage\n"
         )
     }
 
     #[test]
     fn build_multiple_service() {
-        let dockerbuilder = DockerComposeBuilder::new()
-            .add_service("test", "testimage", None, None, None, None)
-            .add_service("test2", "testimage3", None, None, None, None);
-        dbg!(dockerbuilder.build_string());
+        let file = ComposeFile {
+            services: BTreeMap::from([
+                ("test".into(), service("testimage")),
+                ("test2".into(), service("testimage3")),
+            ]),
+            ..Default::default()
+        };
+
         assert_eq!(
-            dockerbuilder.build_string(),
-            "service:\n  test:\n    image: testimage\n  test2:\n    image: testimage3\n"
+            file.to_yaml().unwrap(),
+            "services:\n  test:\n    image: testimage\n  test2:\n    image: testimage3\n"
         )
     }
+
+    #[test]
+    fn writes_volumes_on_separate_lines() {
+        let file = ComposeFile {
+            services: BTreeMap::from([(
+                "db".into(),
+                Service {
+                    volumes: vec![
+                        VolumeMount::volume("data", "/data/db"),
+                        VolumeMount {
+                            mount_type: MountType::Bind,
+                            source: "./init".into(),
+                            target: "/docker-entrypoint-initdb.d".into(),
+                            read_only: true,
+                        },
+                    ],
+                    ..service("mongo")
+                },
+            )]),
+            volumes: BTreeMap::from([
+                ("config".into(), NamedVolume::default()),
+                ("data".into(), NamedVolume::default()),
+            ]),
+            ..Default::default()
+        };
+
+        let yaml = file.to_yaml().unwrap();
+
+        assert!(yaml.contains("\nvolumes:\n  config: {}\n  data: {}\n"));
+        assert!(yaml.contains("type: bind"));
+        assert!(yaml.contains("read_only: true"));
+    }
+
+    #[test]
+    fn round_trips_all_fields() {
+        let file = ComposeFile {
+            services: BTreeMap::from([
+                (
+                    "api".into(),
+                    Service {
+                        container_name: Some("og-api".into()),
+                        command: Some("serve --port 8080".into()),
+                        environment: BTreeMap::from([("MODE".into(), "local".into())]),
+                        ports: vec![PortMapping {
+                            target: 8080,
+                            published: 18080,
+                            host_ip: Some("127.0.0.1".into()),
+                            protocol: Some("udp".into()),
+                        }],
+                        networks: vec!["backend".into()],
+                        depends_on: BTreeMap::from([(
+                            "db".into(),
+                            Dependency {
+                                condition: DependencyCondition::ServiceHealthy,
+                            },
+                        )]),
+                        restart: Some(RestartPolicy::UnlessStopped),
+                        labels: BTreeMap::from([("og-cli.service".into(), "api".into())]),
+                        ..service("api:1.0")
+                    },
+                ),
+                (
+                    "db".into(),
+                    Service {
+                        volumes: vec![VolumeMount::volume("db-data", "/data/db")],
+                        healthcheck: Some(Healthcheck {
+                            test: vec!["CMD".into(), "pg_isready".into()],
+                            interval: Some("10s".into()),
+                            timeout: Some("5s".into()),
+                            retries: Some(5),
+                            start_period: Some("30s".into()),
+                        }),
+                        restart: Some(RestartPolicy::OnFailure),
+                        ..service("postgres:16")
+                    },
+                ),
+            ]),
+            volumes: BTreeMap::from([(
+                "db-data".into(),
+                NamedVolume {
+                    name: Some("og-db-data".into()),
+                    ..Default::default()
+                },
+            )]),
+            networks: BTreeMap::from([(
+                "backend".into(),
+                Network {
+                    driver: Some("bridge".into()),
+                    ..Default::default()
+                },
+            )]),
+        };
+
+        let yaml = file.to_yaml().unwrap();
+
+        assert!(yaml.contains("restart: unless-stopped"));
+        assert!(yaml.contains("condition: service_healthy"));
+        assert_eq!(ComposeFile::from_yaml(&yaml).unwrap(), file);
+    }
+
+    #[test]
+    fn persists_file_per_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ComposeFile {
+            services: BTreeMap::from([("test".into(), service("testimage"))]),
+            ..Default::default()
+        };
+
+        let compose =
+            DockerCompose::new_in(dir.path(), &project_name("mongodb"), &file, None).unwrap();
+
+        assert_eq!(compose.compose_file(), dir.path().join("og-mongodb.yaml"));
+        assert_eq!(
+            ComposeFile::from_yaml(&fs::read_to_string(compose.compose_file()).unwrap()).unwrap(),
+            file
+        );
+    }
+
+    #[test]
+    fn publishes_on_recorded_ports() {
+        let dir = tempfile::tempdir().unwrap();
+        PortRecord::load(&dir.path().join("ports.json"))
+            .unwrap()
+            .remap("og-mongodb/db/27017", 27017, 27018)
+            .unwrap();
+        let file = ComposeFile {
+            services: BTreeMap::from([(
+                "db".into(),
+                Service {
+                    ports: vec![PortMapping {
+                        target: 27017,
+                        published: 27017,
+                        host_ip: None,
+                        protocol: None,
+                    }],
+                    ..service("mongo")
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let compose = DockerCompose::new_in(dir.path(), "og-mongodb", &file, None).unwrap();
+
+        let written =
+            ComposeFile::from_yaml(&fs::read_to_string(compose.compose_file()).unwrap()).unwrap();
+        assert_eq!(written.services["db"].ports[0].published, 27018);
+    }
+
+    #[test]
+    fn fixed_ports_ignore_recorded_ports() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("ports.json"),
+            r#"{"og-mongodb/db/27017":{"requested":27017,"published":27018}}"#,
+        )
+        .unwrap();
+        let file = ComposeFile {
+            services: BTreeMap::from([(
+                "db".into(),
+                Service {
+                    ports: vec![PortMapping {
+                        target: 27017,
+                        published: 27017,
+                        host_ip: None,
+                        protocol: None,
+                    }],
+                    ..service("mongo")
+                },
+            )]),
+            ..Default::default()
+        };
+
+        let moved = DockerCompose::new_in(dir.path(), "og-mongodb", &file, None).unwrap();
+        let fixed =
+            DockerCompose::new_in(dir.path(), "og-mongodb", &file, Some("stop it".into())).unwrap();
+
+        assert_eq!(moved.file.services["db"].ports[0].published, 27018);
+        assert_eq!(fixed.file.services["db"].ports[0].published, 27017);
+    }
+
+    #[test]
+    fn reads_hand_written_files() {
+        let file = ComposeFile::from_yaml(
+            "services:\n  cache:\n    image: redis:7\n    restart: always\nvolumes:\n  cache: {}\n",
+        )
+        .unwrap();
+
+        assert_eq!(file.services["cache"].restart, Some(RestartPolicy::Always));
+        assert!(file.volumes.contains_key("cache"));
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn splits_image_references() {
+        assert_eq!(
+            split_image("mcr.microsoft.com/azure-sql-edge:latest"),
+            ("mcr.microsoft.com/azure-sql-edge", "latest")
+        );
+        assert_eq!(
+            split_image("localhost:5000/mssql"),
+            ("localhost:5000/mssql", "latest")
+        );
+        assert_eq!(split_image("mssql"), ("mssql", "latest"));
+    }
+
+    fn depends_on(services: &[&str]) -> BTreeMap<String, Dependency> {
+        services
+            .iter()
+            .map(|s| {
+                (
+                    s.to_string(),
+                    Dependency {
+                        condition: DependencyCondition::ServiceHealthy,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn splits_commands_like_a_shell() {
+        assert_eq!(
+            split_command("redis-server  --appendonly yes"),
+            ["redis-server", "--appendonly", "yes"]
+        );
+        assert_eq!(
+            split_command(r#"bash -c 'exec mongod --replSet rs0' "a \"b\"" ''"#),
+            ["bash", "-c", "exec mongod --replSet rs0", "a \"b\"", ""]
+        );
+    }
+
+    #[test]
+    fn starts_dependencies_first() {
+        let file = ComposeFile {
+            services: BTreeMap::from([
+                (
+                    "api".into(),
+                    Service {
+                        depends_on: depends_on(&["db", "queue"]),
+                        ..service("api")
+                    },
+                ),
+                ("db".into(), service("postgres")),
+                (
+                    "queue".into(),
+                    Service {
+                        depends_on: depends_on(&["db"]),
+                        ..service("rabbitmq")
+                    },
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(start_order(&file).unwrap(), ["db", "queue", "api"]);
+    }
+
+    #[test]
+    fn rejects_circular_dependencies() {
+        let file = ComposeFile {
+            services: BTreeMap::from([
+                (
+                    "a".into(),
+                    Service {
+                        depends_on: depends_on(&["b"]),
+                        ..service("a")
+                    },
+                ),
+                (
+                    "b".into(),
+                    Service {
+                        depends_on: depends_on(&["a"]),
+                        ..service("b")
+                    },
+                ),
+                ("c".into(), service("c")),
+            ]),
+            ..Default::default()
+        };
+
+        let error = OgError::from(start_order(&file).unwrap_err());
+
+        assert_eq!(error.kind(), ErrorKind::Config);
+        assert!(error.to_string().contains("a, b"));
+    }
+
+    #[test]
+    fn maps_service_to_container() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ComposeFile {
+            services: BTreeMap::from([(
+                "sql".into(),
+                Service {
+                    command: Some("serve --port 1433".into()),
+                    environment: BTreeMap::from([("PASSWORD".into(), "pa$$word".into())]),
+                    ports: vec![
+                        PortMapping {
+                            target: 1433,
+                            published: 14330,
+                            host_ip: None,
+                            protocol: None,
+                        },
+                        PortMapping {
+                            target: 1434,
+                            published: 1434,
+                            host_ip: None,
+                            protocol: Some("udp".into()),
+                        },
+                    ],
+                    volumes: vec![VolumeMount::volume("data", "/var/opt/mssql")],
+                    healthcheck: Some(Healthcheck {
+                        test: vec!["CMD-SHELL".into(), "check $$PASSWORD".into()],
+                        interval: Some("1m30s".into()),
+                        timeout: None,
+                        retries: Some(3),
+                        start_period: Some("1m".into()),
+                    }),
+                    restart: Some(RestartPolicy::UnlessStopped),
+                    ..service("mssql")
+                },
+            )]),
+            volumes: BTreeMap::from([("data".into(), NamedVolume::default())]),
+            ..Default::default()
+        };
+        let compose = DockerCompose::new_in(dir.path(), "og-shop", &file, None).unwrap();
+
+        let config = compose.container_config("sql").unwrap();
+
+        assert_eq!(compose.container_name("sql"), "og-shop-sql-1");
+        assert_eq!(config.cmd.unwrap(), ["serve", "--port", "1433"]);
+        assert_eq!(config.env.unwrap(), ["PASSWORD=pa$word"]);
+        let labels = config.labels.unwrap();
+        assert_eq!(labels[PROJECT_LABEL], "og-shop");
+        assert_eq!(labels[SERVICE_LABEL], "sql");
+        let healthcheck = config.healthcheck.unwrap();
+        assert_eq!(healthcheck.test.unwrap()[1], "check $PASSWORD");
+        assert_eq!(healthcheck.interval, Some(90_000_000_000));
+        assert_eq!(healthcheck.start_period, Some(60_000_000_000));
+        let host = config.host_config.unwrap();
+        let port_bindings = host.port_bindings.unwrap();
+        assert_eq!(
+            port_bindings["1433/tcp"].as_ref().unwrap()[0].host_port,
+            Some("14330".into())
+        );
+        assert!(port_bindings.contains_key("1434/udp"));
+        assert_eq!(
+            host.mounts.unwrap()[0].source.as_deref(),
+            Some("og-shop_data")
+        );
+        assert_eq!(host.network_mode.as_deref(), Some("og-shop_default"));
+        assert_eq!(
+            host.restart_policy.unwrap().name,
+            Some(RestartPolicyNameEnum::UNLESS_STOPPED)
+        );
+    }
+
+    #[test]
+    fn fills_placeholders_only_in_the_container() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = ComposeFile {
+            services: BTreeMap::from([(
+                "db".into(),
+                Service {
+                    environment: BTreeMap::from([(
+                        "PASSWORD".into(),
+                        "${OG_TEST_PASSWORD}".into(),
+                    )]),
+                    ..service("mongo")
+                },
+            )]),
+            ..Default::default()
+        };
+        let compose = DockerCompose::new_in(dir.path(), "og-shop", &file, None)
+            .unwrap()
+            .with_variables(BTreeMap::from([(
+                "OG_TEST_PASSWORD".into(),
+                "s3cret".into(),
+            )]));
+
+        let config = compose.container_config("db").unwrap();
+
+        assert_eq!(config.env.unwrap(), ["PASSWORD=s3cret"]);
+        assert!(!fs::read_to_string(compose.compose_file())
+            .unwrap()
+            .contains("s3cret"));
+    }
+
+    #[test]
+    fn hash_follows_the_definition() {
+        let db = service("postgres:16");
+        let upgraded = service("postgres:17");
+
+        assert_eq!(
+            definition_hash("og-shop", &db).unwrap(),
+            definition_hash("og-shop", &db).unwrap()
+        );
+        assert_ne!(
+            definition_hash("og-shop", &db).unwrap(),
+            definition_hash("og-shop", &upgraded).unwrap()
+        );
+    }
 }
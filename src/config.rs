@@ -7,9 +7,10 @@ use homedir::get_my_home;
 use log::info;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     fs::{self, File},
     io::copy,
-    path::Path,
+    path::{Path, PathBuf},
     sync::OnceLock,
 };
 
@@ -21,6 +22,45 @@ pub struct Config {
     pub sql_password: String,
     pub rancher_base_url: String,
     pub search_urls: SearchUrl,
+    /// Path to the DG CLI binary, defaults to `dg` from the PATH
+    #[serde(default)]
+    pub dg_cli_path: Option<String>,
+    /// URL of the DG CLI installer used by fix-beta
+    #[serde(default)]
+    pub dg_cli_installer_url: Option<String>,
+    /// Pinned SHA-256 of the installer, otherwise `<installer url>.sha256` is used
+    #[serde(default)]
+    pub dg_cli_installer_sha256: Option<String>,
+    /// Defaults for the SQL Server containers of `og sql`
+    #[serde(default)]
+    pub sql: SqlConfig,
+    /// Defaults for the MongoDB container of `og mongodb`
+    #[serde(default)]
+    pub mongodb: MongoDbConfig,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SqlConfig {
+    /// Instance used when `--name` is not passed
+    pub name: Option<String>,
+    pub port: Option<u16>,
+    /// Image for every architecture, takes precedence over `images`
+    pub image: Option<String>,
+    /// Images per host architecture, e.g. `x86_64` or `aarch64`
+    #[serde(default)]
+    pub images: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MongoDbConfig {
+    /// Tag of the `mongo` image, e.g. `6.0` to match the Atlas clusters
+    pub version: Option<String>,
+    pub port: Option<u16>,
+    /// Root user, credentials stored with `og mongodb credentials set` take precedence.
+    /// There is no password field, this file is re-downloaded when it doesn't parse
+    pub username: Option<String>,
+    /// Data volume, defaults to the one older versions created
+    pub volume: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,10 +70,15 @@ pub struct SearchUrl {
     pub oft: String,
 }
 
-pub async fn init_config() -> Result<()> {
-    let og_dir = get_my_home()?
+/// Directory og keeps its config and state in (`~/.og-cli`)
+pub fn og_dir() -> Result<PathBuf> {
+    Ok(get_my_home()?
         .context("Could not get home directory")?
-        .join(".og-cli");
+        .join(".og-cli"))
+}
+
+pub async fn init_config() -> Result<()> {
+    let og_dir = og_dir()?;
     fs::create_dir_all(&og_dir)?;
 
     let ogrc = og_dir.join(".ogrc.json");
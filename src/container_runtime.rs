@@ -0,0 +1,282 @@
+use bollard::{Docker, API_DEFAULT_VERSION};
+use eyre::{Context, Result};
+use homedir::get_my_home;
+use log::debug;
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::Duration,
+};
+
+use crate::doctor::{DoctorFailure, DoctorSuccess};
+
+/// Seconds bollard waits for a response
+const TIMEOUT: u64 = 120;
+/// The doctor shouldn't wait long for an endpoint that doesn't answer
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+#[cfg(unix)]
+const DEFAULT_HOST: &str = "unix:///var/run/docker.sock";
+#[cfg(windows)]
+const DEFAULT_HOST: &str = "npipe:////./pipe/docker_engine";
+
+/// Engine behind the Docker API og talks to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeKind {
+    Docker,
+    Podman,
+    Colima,
+    RancherDesktop,
+}
+
+impl Display for RuntimeKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RuntimeKind::Docker => "Docker",
+            RuntimeKind::Podman => "Podman",
+            RuntimeKind::Colima => "Colima",
+            RuntimeKind::RancherDesktop => "Rancher Desktop",
+        })
+    }
+}
+
+/// How the endpoint was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostSource {
+    /// `DOCKER_HOST` is set
+    Environment,
+    /// A socket of a known runtime exists
+    Socket,
+    /// Nothing was found, the platform default is tried
+    Default,
+}
+
+/// The container runtime og talks to through the Docker API
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerRuntime {
+    pub kind: RuntimeKind,
+    /// Docker API endpoint like `unix:///var/run/docker.sock`
+    pub host: String,
+    pub source: HostSource,
+}
+
+/// The runtime detected on first use
+pub fn runtime() -> &'static ContainerRuntime {
+    RUNTIME.get_or_init(|| {
+        let runtime = ContainerRuntime::detect();
+        debug!("Using {} at {}", runtime.kind, runtime.host);
+        runtime
+    })
+}
+
+/// Connects to the Docker API of the detected runtime
+pub fn connect() -> Result<Docker> {
+    runtime().connect()
+}
+
+impl ContainerRuntime {
+    fn detect() -> ContainerRuntime {
+        let home = get_my_home().ok().flatten();
+        let xdg_runtime_dir = env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+
+        detect_with(
+            env::var("DOCKER_HOST").ok().filter(|h| !h.is_empty()),
+            &candidates(home.as_deref(), xdg_runtime_dir.as_deref()),
+            |path| path.exists(),
+            |path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        )
+    }
+
+    pub fn connect(&self) -> Result<Docker> {
+        let docker = if self.host.starts_with("tcp://") || self.host.starts_with("http://") {
+            Docker::connect_with_http(&self.host, TIMEOUT, API_DEFAULT_VERSION)
+        } else {
+            Docker::connect_with_local(&self.host, TIMEOUT, API_DEFAULT_VERSION)
+        };
+
+        docker.with_context(|| format!("Could not connect to {} at {}", self.kind, self.host))
+    }
+
+    /// Whether the engine answers a ping on the endpoint
+    pub fn is_running(&self) -> bool {
+        let Ok(docker) = self.connect() else {
+            return false;
+        };
+
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current()
+                .block_on(tokio::time::timeout(PING_TIMEOUT, docker.ping()))
+        })
+        .is_ok_and(|ping| ping.is_ok())
+    }
+
+    /// Doctor check naming the runtime, shared by the plugins that need one
+    pub fn doctor(&self, plugin: &str) -> Result<DoctorSuccess, DoctorFailure> {
+        let source = match self.source {
+            HostSource::Environment => " from DOCKER_HOST",
+            HostSource::Socket | HostSource::Default => "",
+        };
+
+        if self.is_running() {
+            Ok(DoctorSuccess {
+                message: format!("{} is running at {}{source}", self.kind, self.host),
+                plugin: plugin.into(),
+            })
+        } else {
+            Err(DoctorFailure {
+                message: format!(
+                    "{} at {}{source} is not running or might not be installed",
+                    self.kind, self.host
+                ),
+                plugin: plugin.into(),
+                fix: None,
+            })
+        }
+    }
+}
+
+/// Sockets of the runtimes in the order they are tried, `/var/run/docker.sock` is often a link to one of the others
+fn candidates(home: Option<&Path>, xdg_runtime_dir: Option<&Path>) -> Vec<PathBuf> {
+    let mut candidates = vec![PathBuf::from("/var/run/docker.sock")];
+    if let Some(home) = home {
+        candidates.extend([
+            home.join(".docker/run/docker.sock"),
+            home.join(".rd/docker.sock"),
+            home.join(".colima/default/docker.sock"),
+            home.join(".colima/docker.sock"),
+        ]);
+    }
+    if let Some(xdg_runtime_dir) = xdg_runtime_dir {
+        candidates.push(xdg_runtime_dir.join("podman/podman.sock"));
+    }
+    candidates.push(PathBuf::from("/run/podman/podman.sock"));
+    if let Some(home) = home {
+        candidates.push(home.join(".local/share/containers/podman/machine/qemu/podman.sock"));
+    }
+
+    candidates
+}
+
+fn detect_with(
+    docker_host: Option<String>,
+    candidates: &[PathBuf],
+    exists: impl Fn(&Path) -> bool,
+    resolve: impl Fn(&Path) -> PathBuf,
+) -> ContainerRuntime {
+    if let Some(host) = docker_host {
+        let kind = classify(Path::new(host.trim_start_matches("unix://")), &resolve);
+        return ContainerRuntime {
+            kind,
+            host,
+            source: HostSource::Environment,
+        };
+    }
+
+    match candidates.iter().find(|c| exists(c)) {
+        Some(socket) => ContainerRuntime {
+            kind: classify(socket, &resolve),
+            host: format!("unix://{}", socket.display()),
+            source: HostSource::Socket,
+        },
+        None => ContainerRuntime {
+            kind: RuntimeKind::Docker,
+            host: DEFAULT_HOST.to_string(),
+            source: HostSource::Default,
+        },
+    }
+}
+
+/// Tells the runtimes apart by where their socket lives
+fn classify(socket: &Path, resolve: impl Fn(&Path) -> PathBuf) -> RuntimeKind {
+    let path = resolve(socket).to_string_lossy().to_lowercase();
+    if path.contains("podman") {
+        RuntimeKind::Podman
+    } else if path.contains(".colima") {
+        RuntimeKind::Colima
+    } else if path.contains("/.rd/") || path.contains("rancher-desktop") {
+        RuntimeKind::RancherDesktop
+    } else {
+        RuntimeKind::Docker
+    }
+}
+
+static RUNTIME: OnceLock<ContainerRuntime> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(
+        docker_host: Option<&str>,
+        existing: &[&str],
+        links: &[(&str, &str)],
+    ) -> ContainerRuntime {
+        let candidates = candidates(
+            Some(Path::new("/home/dev")),
+            Some(Path::new("/run/user/1000")),
+        );
+        detect_with(
+            docker_host.map(String::from),
+            &candidates,
+            |p| existing.iter().any(|e| Path::new(e) == p),
+            |p| {
+                links
+                    .iter()
+                    .find(|(from, _)| Path::new(from) == p)
+                    .map(|(_, to)| PathBuf::from(to))
+                    .unwrap_or_else(|| p.to_path_buf())
+            },
+        )
+    }
+
+    #[test]
+    fn docker_host_wins() {
+        let runtime = detect(
+            Some("unix:///run/user/1000/podman/podman.sock"),
+            &["/var/run/docker.sock"],
+            &[],
+        );
+
+        assert_eq!(runtime.kind, RuntimeKind::Podman);
+        assert_eq!(runtime.host, "unix:///run/user/1000/podman/podman.sock");
+        assert_eq!(runtime.source, HostSource::Environment);
+    }
+
+    #[test]
+    fn follows_default_socket_link() {
+        let runtime = detect(
+            None,
+            &["/var/run/docker.sock", "/home/dev/.rd/docker.sock"],
+            &[("/var/run/docker.sock", "/home/dev/.rd/docker.sock")],
+        );
+
+        assert_eq!(runtime.kind, RuntimeKind::RancherDesktop);
+        assert_eq!(runtime.host, "unix:///var/run/docker.sock");
+    }
+
+    #[test]
+    fn finds_runtime_sockets() {
+        assert_eq!(
+            detect(None, &["/home/dev/.colima/default/docker.sock"], &[]).kind,
+            RuntimeKind::Colima
+        );
+        assert_eq!(
+            detect(None, &["/run/user/1000/podman/podman.sock"], &[]).host,
+            "unix:///run/user/1000/podman/podman.sock"
+        );
+        assert_eq!(
+            detect(None, &["/var/run/docker.sock"], &[]).kind,
+            RuntimeKind::Docker
+        );
+    }
+
+    #[test]
+    fn falls_back_to_default() {
+        let runtime = detect(None, &[], &[]);
+
+        assert_eq!(runtime.host, DEFAULT_HOST);
+        assert_eq!(runtime.source, HostSource::Default);
+    }
+}
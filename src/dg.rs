@@ -1,54 +1,139 @@
 use clap::Args;
-use eyre::Result;
-use std::process::Command;
+use eyre::WrapErr;
+use std::process::{Command, ExitStatus, Stdio};
+
+use crate::{
+    error::{ErrorKind, Result, ResultExt},
+    get_config,
+};
+
+pub const DG_CLI_USER_TYPE: &str = "autonomous";
 
 /// Passthrough to DG CLI
 #[derive(Debug, Args)]
-pub struct DgCommand;
-// {
-//     #[command(subcommand)]
-//     command: DgSubCommands,
-// }
-//
-// #[derive(Subcommand, Debug)]
-// pub  struct DgSubCommands{
-//     plugin { additional_params: Option<String> },
-//
-// }
+pub struct DgCommand {
+    /// Arguments forwarded to the DG CLI, e.g. `og dg -- plugin list`
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    args: Vec<String>,
+}
 
 pub struct DgCliPlugin;
 
 impl DgCliPlugin {
-    pub fn run(_: DgCommand) -> Result<()> {
-        let dg_path = "dg";
-        Command::new(dg_path)
-            .env("DG_CLI_USER_TYPE", "autonomous")
-            .args(["--help"])
-            .spawn()?
-            .wait()?;
+    pub fn run(cli: DgCommand) -> Result<i32> {
+        Self::run_from_plain_args(cli.args)
+    }
 
-        Ok(())
+    /// Runs the DG CLI with the terminal attached and returns its exit code
+    pub fn run_from_plain_args(args: Vec<String>) -> Result<i32> {
+        let status = dg_command()
+            .args(args)
+            .stdin(Stdio::inherit())
+            .stdout(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .status()
+            .wrap_err_with(|| format!("Unable to run the DG CLI at '{}'", dg_path()))
+            .error_kind(ErrorKind::ExternalTool)?;
+
+        Ok(exit_code(status))
     }
 
-    pub fn run2() -> Result<()> {
-        let dg_path = "dg";
-        Command::new(dg_path)
-            .env("DG_CLI_USER_TYPE", "autonomous")
-            .args(["--help"])
-            .spawn()?
-            .wait()?;
+    /// Commands offered by the DG CLI, as listed in its own help output
+    pub fn list_commands() -> Option<String> {
+        let output = dg_command()
+            .arg("--help")
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()?;
 
-        Ok(())
+        if !output.status.success() {
+            return None;
+        }
+
+        parse_help_commands(&String::from_utf8_lossy(&output.stdout))
     }
+}
 
-    pub fn run_from_plain_args(args: Vec<String>) -> Result<()> {
-        let dg_path = "dg";
-        Command::new(dg_path)
-            .env("DG_CLI_USER_TYPE", "autonomous")
-            .args(args)
-            .spawn()?
-            .wait()?;
+pub fn dg_path() -> &'static str {
+    get_config().dg_cli_path.as_deref().unwrap_or("dg")
+}
+
+pub fn dg_command() -> Command {
+    let mut command = Command::new(dg_path());
+    command.env("DG_CLI_USER_TYPE", DG_CLI_USER_TYPE);
+    command
+}
+
+#[cfg(target_family = "unix")]
+fn exit_code(status: ExitStatus) -> i32 {
+    use std::os::unix::process::ExitStatusExt;
+
+    // Mirror the shell convention for children terminated by a signal
+    match status.signal() {
+        Some(signal) => 128 + signal,
+        None => status.code().unwrap_or(1),
+    }
+}
+
+#[cfg(target_family = "windows")]
+fn exit_code(status: ExitStatus) -> i32 {
+    status.code().unwrap_or(1)
+}
+
+/// Extracts the command section of a click (`Commands:`) or rich (`╭─ Commands ─╮`) help text
+fn parse_help_commands(help: &str) -> Option<String> {
+    let commands: Vec<String> = help
+        .lines()
+        .skip_while(|l| !is_commands_header(l))
+        .skip(1)
+        .map_while(|l| {
+            let trimmed = l.trim();
+            if trimmed.is_empty() || trimmed.starts_with('╰') || !l.starts_with([' ', '│']) {
+                return None;
+            }
+            Some(format!("  {}", trimmed.trim_matches('│').trim()))
+        })
+        .collect();
+
+    if commands.is_empty() {
+        None
+    } else {
+        Some(commands.join("\n"))
+    }
+}
+
+fn is_commands_header(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed == "Commands:" || (trimmed.starts_with('╭') && trimmed.contains(" Commands "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_click_help_commands() {
+        let help = "Usage: dg [OPTIONS] COMMAND [ARGS]...\n\nOptions:\n  --help  Show this message and exit.\n\nCommands:\n  login   Log in to DG\n  plugin  Manage plugins\n";
+
+        assert_eq!(
+            parse_help_commands(help),
+            Some("  login   Log in to DG\n  plugin  Manage plugins".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_rich_help_commands() {
+        let help = " Usage: dg [OPTIONS] COMMAND [ARGS]...\n\n╭─ Options ──────────────────╮\n│ --help  Show this message. │\n╰────────────────────────────╯\n╭─ Commands ─────────────────╮\n│ login   Log in to DG       │\n│ plugin  Manage plugins     │\n╰────────────────────────────╯\n";
+
+        assert_eq!(
+            parse_help_commands(help),
+            Some("  login   Log in to DG\n  plugin  Manage plugins".to_string())
+        );
+    }
 
-        Ok(())
+    #[test]
+    fn parse_help_without_commands() {
+        assert_eq!(parse_help_commands("Usage: dg [OPTIONS]\n"), None);
     }
 }
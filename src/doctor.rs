@@ -1,11 +1,16 @@
 use clap::Args;
 use log::{error, info};
-use std::process::Command;
 use which::which;
 
 #[cfg(feature = "git")]
 use crate::git;
-use crate::{dotnet, fix, kube, mongo_db, network, plugin::Plugin, sql};
+use crate::{
+    dotnet, env,
+    error::{ErrorKind, OgError, Result},
+    fix, kube, mongo_db, network,
+    plugin::Plugin,
+    sql,
+};
 
 /// Detect and fix problems
 #[derive(Args)]
@@ -25,13 +30,14 @@ pub struct DoctorFailure {
     pub fix: Option<Box<dyn Fn() -> Result<(), String>>>,
 }
 
-pub fn run(dr_command: DoctorCommand) {
+pub fn run(dr_command: DoctorCommand) -> Result<()> {
     let plugins: Vec<Box<dyn Plugin>> = vec![
         Box::new(fix::FixPlugin),
         #[cfg(feature = "git")]
         Box::new(git::GitPlugin),
         Box::new(mongo_db::MongoDbPlugin),
         Box::new(sql::SqlPlugin),
+        Box::new(env::EnvPlugin),
         Box::new(kube::KubernetesPlugin),
         Box::new(dotnet::DotnetPlugin),
         Box::new(network::NetworkPlugin),
@@ -69,6 +75,19 @@ pub fn run(dr_command: DoctorCommand) {
             },
         }
     }
+
+    let failed_checks = doctor_result_with_fixes
+        .iter()
+        .filter(|r| !matches!(r, Ok(_) | Err((_, Some(Ok(_))))))
+        .count();
+    if failed_checks > 0 {
+        return Err(OgError::msg(
+            ErrorKind::Other,
+            format!("{failed_checks} doctor checks failed"),
+        ));
+    }
+
+    Ok(())
 }
 pub fn is_command_in_path(command: &str) -> Result<DoctorSuccess, DoctorFailure> {
     let res = match which(command) {
@@ -1,6 +1,6 @@
 use clap::{Args, Subcommand};
 use dialoguer::Select;
-use eyre::{Context, ContextCompat, Ok, Result};
+use eyre::{Context, ContextCompat, Result};
 use glob::glob;
 use log::info;
 use regex::Regex;
@@ -12,7 +12,9 @@ use std::{
 
 use crate::{
     doctor::{DoctorFailure, DoctorSuccess},
+    error::{self, ErrorKind, OgError, ResultExt},
     plugin::Plugin,
+    prompt,
 };
 
 /// .NET helpers
@@ -28,6 +30,12 @@ enum DotnetSubcommands {
         additional_params: Option<String>,
         #[arg(short, long)]
         dry_run: bool,
+        /// Project to run (path or name of the .csproj) instead of selecting it interactively
+        #[arg(short, long)]
+        project: Option<String>,
+        /// Launch profile to use instead of selecting it interactively
+        #[arg(short, long)]
+        launch_profile: Option<String>,
     },
 }
 
@@ -50,13 +58,17 @@ fn env_var_regex() -> Result<regex::Regex> {
 }
 
 impl DotnetPlugin {
-    pub fn run(cli: DotnetCommand) -> Result<()> {
+    pub fn run(cli: DotnetCommand) -> error::Result<()> {
         match cli.command {
             DotnetSubcommands::Run {
                 additional_params,
                 dry_run,
-            } => dotnet_run(additional_params, dry_run),
+                project,
+                launch_profile,
+            } => dotnet_run(additional_params, dry_run, project, launch_profile)?,
         }
+
+        Ok(())
     }
 }
 
@@ -69,7 +81,7 @@ fn get_projects_with_launch_settings() -> Result<Vec<ProjectsWithLaunchSettings>
     let projects = glob("**/*.csproj")?;
     let g = projects
         .into_iter()
-        .map(|p| {
+        .map(|p| -> Result<_> {
             let path = p?;
             let settings = get_launch_setting_names(&path);
             Ok((path, settings))
@@ -111,7 +123,12 @@ fn get_launch_setting_names(project_path: &Path) -> Result<Vec<String>> {
     Ok(names)
 }
 
-fn dotnet_run(additional_params: Option<String>, dry_run: bool) -> Result<()> {
+fn dotnet_run(
+    additional_params: Option<String>,
+    dry_run: bool,
+    project: Option<String>,
+    launch_profile: Option<String>,
+) -> Result<()> {
     let launch_settings = get_projects_with_launch_settings()?;
     let project_items: Vec<_> = launch_settings
         .iter()
@@ -122,16 +139,34 @@ fn dotnet_run(additional_params: Option<String>, dry_run: bool) -> Result<()> {
         .last()
         .context("I cannot find any dotnet projects with launch settings")?;
 
-    let selected_proj = Select::new()
-        .with_prompt("Select project")
-        .items(&project_items)
-        .interact()?;
+    let selected_proj = match project {
+        Some(project) => launch_settings
+            .iter()
+            .position(|l| is_project(&l.path_to_csproj, &project))
+            .with_context(|| format!("No project {project} with launch settings found"))?,
+        None => {
+            prompt::ensure_interactive("--project <PROJECT>")?;
+            Select::new()
+                .with_prompt("Select project")
+                .items(&project_items)
+                .interact()?
+        }
+    };
 
     let select_launch = &launch_settings[selected_proj].launch_settings;
-    let selected_launch_name = Select::new()
-        .with_prompt("Select launch settings")
-        .items(&select_launch)
-        .interact()?;
+    let selected_launch_name = match launch_profile {
+        Some(launch_profile) => select_launch
+            .iter()
+            .position(|l| *l == launch_profile)
+            .with_context(|| format!("No launch profile {launch_profile} found"))?,
+        None => {
+            prompt::ensure_interactive("--launch-profile <NAME>")?;
+            Select::new()
+                .with_prompt("Select launch settings")
+                .items(select_launch)
+                .interact()?
+        }
+    };
 
     let launch_setting_name = &launch_settings[selected_proj].launch_settings[selected_launch_name];
     let project_path = launch_settings[selected_proj]
@@ -154,18 +189,33 @@ fn dotnet_run(additional_params: Option<String>, dry_run: bool) -> Result<()> {
         info!("  args:");
         args.iter().for_each(|a| info!("    {}", a));
     } else {
-        Command::new("dotnet")
+        let status = Command::new("dotnet")
             .current_dir(project_path)
             .arg("run")
             .args(args)
-            .spawn()
-            .expect("Could not run dotnet command")
-            .wait()?;
+            .status()
+            .context("Could not run dotnet command")
+            .error_kind(ErrorKind::ExternalTool)?;
+
+        if !status.success() {
+            Err(OgError::msg(
+                ErrorKind::ExternalTool,
+                format!("dotnet run failed with {status}"),
+            ))?;
+        }
     }
     Ok(())
 }
 
-fn is_dotnet_installed() -> core::result::Result<DoctorSuccess, DoctorFailure> {
+/// Matches a project by the path to its .csproj or its name
+fn is_project(path_to_csproj: &Path, project: &str) -> bool {
+    path_to_csproj == Path::new(project)
+        || path_to_csproj
+            .file_stem()
+            .is_some_and(|s| s.eq_ignore_ascii_case(project))
+}
+
+fn is_dotnet_installed() -> Result<DoctorSuccess, DoctorFailure> {
     let cmd_result = Command::new("dotnet")
         .arg("--version")
         .stdout(std::process::Stdio::null())
@@ -173,11 +223,11 @@ fn is_dotnet_installed() -> core::result::Result<DoctorSuccess, DoctorFailure> {
         .output();
 
     match cmd_result {
-        core::result::Result::Ok(_) => core::result::Result::Ok(DoctorSuccess {
+        Ok(_) => Ok(DoctorSuccess {
             message: "dotnet is installed".to_string(),
             plugin: "dotnet".to_string(),
         }),
-        core::result::Result::Err(_) => core::result::Result::Err(DoctorFailure {
+        Err(_) => Err(DoctorFailure {
             message: "Dotnet is not available. Make sure it is installed".to_string(),
             plugin: "dotnet".to_string(),
             fix: None,
@@ -199,6 +249,16 @@ mod tests {
         assert!(!regex.is_match("\"Erp - Dev\": \"something\" {"));
     }
 
+    #[test]
+    fn test_is_project() {
+        let path = Path::new("src/Erp.Api/Erp.Api.csproj");
+
+        assert!(is_project(path, "src/Erp.Api/Erp.Api.csproj"));
+        assert!(is_project(path, "Erp.Api"));
+        assert!(is_project(path, "erp.api"));
+        assert!(!is_project(path, "Erp"));
+    }
+
     #[test]
     fn test_the_regex_capturing() {
         let regex = lauch_settings_regex().expect("Regex fails");
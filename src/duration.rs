@@ -0,0 +1,169 @@
+use std::time::{Duration, SystemTime};
+
+/// Parses durations like `90`, `30s`, `5m` or `1h` for command line flags, plain numbers are seconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("'{value}' is not a duration like 30s, 5m or 1h"))?;
+    let factor = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        _ => return Err(format!("Unknown unit '{unit}', use s, m or h")),
+    };
+    let seconds = amount
+        .checked_mul(factor)
+        .ok_or_else(|| format!("'{value}' is too long"))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parses Go durations as used in compose files, like `10s`, `1m30s` or `500ms`
+pub fn parse_compose_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("'{value}' is not a duration like 30s, 1m30s or 500ms");
+    let mut rest = value.trim();
+    if rest == "0" {
+        return Ok(Duration::ZERO);
+    }
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut nanos = 0f64;
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let (amount, tail) = rest.split_at(split);
+        let split = tail
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(split);
+
+        let amount: f64 = amount.parse().map_err(|_| invalid())?;
+        let factor = match unit {
+            "ns" => 1.0,
+            "us" | "µs" => 1e3,
+            "ms" => 1e6,
+            "s" => 1e9,
+            "m" => 60e9,
+            "h" => 3600e9,
+            _ => return Err(invalid()),
+        };
+        nanos += amount * factor;
+        rest = tail;
+    }
+
+    if nanos >= u64::MAX as f64 {
+        return Err(format!("'{value}' is too long"));
+    }
+    Ok(Duration::from_nanos(nanos as u64))
+}
+
+/// Formats the two largest units, e.g. `2h 5m`
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{seconds}s"),
+        (0, 0, m) => format!("{m}m {}s", seconds % 60),
+        (0, h, m) => format!("{h}h {m}m"),
+        (d, h, _) => format!("{d}d {h}h"),
+    }
+}
+
+/// Parses UTC timestamps like `2024-06-01T12:34:56.123456789Z` as returned by the Docker API
+pub fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let (date_time, rest) = (value.get(..19)?, value.get(19..)?);
+    if !(rest.starts_with('.') || rest.starts_with('Z')) || !rest.ends_with('Z') {
+        return None;
+    }
+
+    let number = |range: std::ops::Range<usize>| date_time.get(range)?.parse::<i64>().ok();
+    let days = days_from_civil(number(0..4)?, number(5..7)?, number(8..10)?);
+    let seconds = days * 86400 + number(11..13)? * 3600 + number(14..16)? * 60 + number(17..19)?;
+
+    // Docker reports never started containers as 0001-01-01T00:00:00Z
+    let seconds = u64::try_from(seconds).ok().filter(|s| *s > 0)?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("60s"), Ok(Duration::from_secs(60)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn parses_compose_durations() {
+        assert_eq!(parse_compose_duration("1m30s"), Ok(Duration::from_secs(90)));
+        assert_eq!(
+            parse_compose_duration("500ms"),
+            Ok(Duration::from_millis(500))
+        );
+        assert_eq!(
+            parse_compose_duration("1.5h"),
+            Ok(Duration::from_secs(5400))
+        );
+        assert_eq!(parse_compose_duration("0"), Ok(Duration::ZERO));
+        assert!(parse_compose_duration("10").is_err());
+        assert!(parse_compose_duration("5d").is_err());
+    }
+
+    #[test]
+    fn formats_largest_units() {
+        assert_eq!(format_duration(Duration::from_secs(42)), "42s");
+        assert_eq!(format_duration(Duration::from_secs(303)), "5m 3s");
+        assert_eq!(format_duration(Duration::from_secs(7500)), "2h 5m");
+        assert_eq!(
+            format_duration(Duration::from_secs(3 * 86400 + 4 * 3600)),
+            "3d 4h"
+        );
+    }
+
+    #[test]
+    fn parses_docker_timestamps() {
+        assert_eq!(
+            parse_timestamp("2024-06-01T12:34:56.123456789Z"),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1717245296))
+        );
+        assert_eq!(
+            parse_timestamp("1970-01-01T00:00:01Z"),
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1))
+        );
+        assert_eq!(parse_timestamp("0001-01-01T00:00:00Z"), None);
+        assert_eq!(parse_timestamp("2024-06-01 12:34:56"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert!(parse_duration("s").is_err());
+        assert!(parse_duration("999999999999999999h").is_err());
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration("").is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use clap::{Args, Subcommand};
+use std::time::Duration;
+
+use crate::duration::parse_duration;
+
+/// Run the local services a project declares in og.yaml
+#[derive(Args, Debug)]
+pub struct EnvCommand {
+    #[command(subcommand)]
+    pub command: EnvSubcommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum EnvSubcommands {
+    /// Start the services and wait until they are healthy
+    Up(UpArgs),
+    /// Stop the services
+    Down(DownArgs),
+    /// Show the services and how to connect to them
+    Status,
+}
+
+#[derive(Args, Debug)]
+pub struct UpArgs {
+    /// How long to wait for the services to become healthy, e.g. 90s or 5m
+    #[arg(long, value_parser = parse_duration, default_value = "3m")]
+    pub timeout: Duration,
+}
+
+#[derive(Args, Debug)]
+pub struct DownArgs {
+    /// Also delete the volumes with all data of the services
+    #[arg(short, long)]
+    pub volumes: bool,
+}
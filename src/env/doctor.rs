@@ -0,0 +1,23 @@
+use super::EnvPlugin;
+use crate::{
+    doctor::{DoctorFailure, DoctorSuccess},
+    plugin::Plugin,
+    project::{Project, PROJECT_FILE},
+};
+
+impl Plugin for EnvPlugin {
+    fn doctor(&self) -> Vec<Result<DoctorSuccess, DoctorFailure>> {
+        match Project::discover() {
+            Ok(Some(project)) => vec![Ok(DoctorSuccess {
+                message: format!("{} is valid", project.root.join(PROJECT_FILE).display()),
+                plugin: "Env".into(),
+            })],
+            Ok(None) => vec![],
+            Err(error) => vec![Err(DoctorFailure {
+                message: format!("{error:#}"),
+                plugin: "Env".into(),
+                fix: None,
+            })],
+        }
+    }
+}
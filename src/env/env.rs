@@ -0,0 +1,233 @@
+use bollard::container::{InspectContainerOptions, ListContainersOptions};
+use eyre::Result;
+use log::info;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{
+    catalog::{CatalogService, ServiceSettings, SQL_PASSWORD_VARIABLE},
+    commands::{EnvCommand, EnvSubcommands},
+};
+use crate::{
+    common_docker::{project_name, ComposeFile, DockerCompose, PROJECT_LABEL, SERVICE_LABEL},
+    container_runtime,
+    error::{self, ErrorKind, OgError},
+    get_config, output,
+    output::Table,
+    project::{Project, PROJECT_FILE},
+    prompt,
+};
+
+pub struct EnvPlugin;
+
+impl EnvPlugin {
+    pub async fn run(cli: EnvCommand) -> error::Result<()> {
+        let stack = Stack::discover()?;
+        let mut compose = stack.compose()?;
+
+        match cli.command {
+            EnvSubcommands::Up(args) => {
+                info!(
+                    "Starting {} as {}, waiting up to {}s for them to be healthy...",
+                    stack.service_names(),
+                    stack.project,
+                    args.timeout.as_secs()
+                );
+                compose.start_and_wait(args.timeout).await?;
+                print_status(&stack, &compose).await?;
+            }
+            EnvSubcommands::Down(args) => {
+                if args.volumes {
+                    if !prompt::confirm(
+                        &format!(
+                            "Delete the volumes of {}? All data of {} will be lost",
+                            stack.project,
+                            stack.service_names()
+                        ),
+                        false,
+                    )? {
+                        Err(OgError::msg(
+                            ErrorKind::UserAbort,
+                            format!("The volumes of {} were not deleted", stack.project),
+                        ))?;
+                    }
+                    compose.stop_and_remove_volumes().await?;
+                } else {
+                    compose.stop().await?;
+                }
+                info!("Stopped {}", stack.project);
+            }
+            EnvSubcommands::Status => print_status(&stack, &compose).await?,
+        }
+
+        Ok(())
+    }
+}
+
+/// The services of a project's `og.yaml`, run as one compose project
+#[derive(Debug, PartialEq)]
+struct Stack {
+    project: String,
+    services: Vec<(CatalogService, ServiceSettings)>,
+}
+
+impl Stack {
+    fn discover() -> error::Result<Stack> {
+        let project = Project::discover()?;
+        let Some((project, env)) = project.and_then(|p| Some((p.root, p.config.env?))) else {
+            return Err(OgError::msg(
+                ErrorKind::Usage,
+                format!("No services found, declare them under env.services in {PROJECT_FILE}"),
+            ));
+        };
+
+        let name = env.name.unwrap_or_else(|| {
+            project
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        Ok(Stack {
+            project: project_name(&sanitize_name(&name)),
+            services: env
+                .services
+                .into_iter()
+                .map(|(service, settings)| (service, settings.unwrap_or_default()))
+                .collect(),
+        })
+    }
+
+    /// Ports of the whole stack stay fixed if one of its services advertises its address
+    fn compose(&self) -> Result<DockerCompose> {
+        let file = self.compose_file();
+        let compose = if self.services.iter().any(|(s, _)| s.advertises_address()) {
+            DockerCompose::with_fixed_ports(
+                &self.project,
+                &file,
+                &format!("set another port under env.services in {PROJECT_FILE}"),
+            )?
+        } else {
+            DockerCompose::new(&self.project, &file)?
+        };
+
+        Ok(compose.with_variables(BTreeMap::from([(
+            SQL_PASSWORD_VARIABLE.to_string(),
+            get_config().sql_password.clone(),
+        )])))
+    }
+
+    fn compose_file(&self) -> ComposeFile {
+        ComposeFile {
+            services: self
+                .services
+                .iter()
+                .map(|(service, settings)| {
+                    (
+                        service.name().to_string(),
+                        service.compose_service(settings),
+                    )
+                })
+                .collect(),
+            volumes: self
+                .services
+                .iter()
+                .map(|(service, _)| (service.volume_name(), Default::default()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn service_names(&self) -> String {
+        self.services
+            .iter()
+            .map(|(service, _)| service.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+async fn print_status(stack: &Stack, compose: &DockerCompose) -> Result<()> {
+    let docker = container_runtime::connect()?;
+    let label = format!("{PROJECT_LABEL}={}", stack.project);
+    let options = ListContainersOptions {
+        all: true,
+        filters: HashMap::from([("label", vec![label.as_str()])]),
+        ..Default::default()
+    };
+    let containers = docker.list_containers(Some(options)).await?;
+
+    let mut rows = Vec::new();
+    for service in stack.services.iter().map(|(service, _)| service) {
+        let container = containers.iter().find(|c| {
+            c.labels
+                .as_ref()
+                .and_then(|l| l.get(SERVICE_LABEL))
+                .is_some_and(|s| s == service.name())
+        });
+        // Ports of a container that isn't created yet come from the compose file
+        let published = |target: u16| {
+            container
+                .and_then(|c| {
+                    c.ports
+                        .iter()
+                        .flatten()
+                        .find(|p| p.private_port == target)
+                        .and_then(|p| p.public_port)
+                })
+                .or_else(|| compose.published_port(service.name(), target))
+                .unwrap_or(target)
+        };
+        let port = published(service.container_port());
+        let health = match container.and_then(|c| c.id.as_deref()) {
+            Some(id) => docker
+                .inspect_container(id, None::<InspectContainerOptions>)
+                .await?
+                .state
+                .and_then(|s| s.health)
+                .and_then(|h| h.status)
+                .map(|h| h.to_string())
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+
+        rows.push(vec![
+            service.name().to_string(),
+            container
+                .and_then(|c| c.state.clone())
+                .unwrap_or_else(|| "not created".to_string()),
+            health,
+            port.to_string(),
+            service.connection_info(published, &get_config().sql_password),
+        ]);
+    }
+
+    output::print_table(&Table {
+        columns: ["service", "state", "health", "port", "connection"]
+            .map(String::from)
+            .to_vec(),
+        rows,
+    });
+
+    Ok(())
+}
+
+/// Compose project names only allow lowercase letters, digits, `-` and `_`
+fn sanitize_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.to_ascii_lowercase() {
+            c @ ('a'..='z' | '0'..='9' | '-' | '_') => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_project_names() {
+        assert_eq!(sanitize_name("Shop.Api"), "shop-api");
+        assert_eq!(sanitize_name("erp_v2"), "erp_v2");
+    }
+}
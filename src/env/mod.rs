@@ -0,0 +1,8 @@
+mod catalog;
+mod commands;
+mod doctor;
+mod env;
+
+pub use catalog::{CatalogService, ServiceSettings};
+pub use commands::EnvCommand;
+pub use env::EnvPlugin;
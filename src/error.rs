@@ -0,0 +1,170 @@
+use std::{
+    fmt::{Debug, Display, Formatter},
+    io,
+};
+
+pub type Result<T, E = OgError> = std::result::Result<T, E>;
+
+/// Category of an error, each one maps to a documented process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Other,
+    Usage,
+    Config,
+    Network,
+    Auth,
+    Docker,
+    ExternalTool,
+    UserAbort,
+}
+
+impl ErrorKind {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Usage => 2,
+            ErrorKind::Config => 3,
+            ErrorKind::Network => 4,
+            ErrorKind::Auth => 5,
+            ErrorKind::Docker => 6,
+            ErrorKind::ExternalTool => 7,
+            ErrorKind::UserAbort => 130,
+        }
+    }
+}
+
+/// The error returned by all og plugins
+pub struct OgError {
+    kind: ErrorKind,
+    report: eyre::Report,
+}
+
+impl OgError {
+    pub fn new(kind: ErrorKind, report: impl Into<eyre::Report>) -> Self {
+        Self {
+            kind,
+            report: report.into(),
+        }
+    }
+
+    pub fn msg<M>(kind: ErrorKind, message: M) -> Self
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        Self::new(kind, eyre::Report::msg(message))
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        self.kind.exit_code()
+    }
+}
+
+impl Display for OgError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.report, f)
+    }
+}
+
+impl Debug for OgError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.report, f)
+    }
+}
+
+impl std::error::Error for OgError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.report.source()
+    }
+}
+
+impl From<eyre::Report> for OgError {
+    fn from(report: eyre::Report) -> Self {
+        let kind = classify(&report);
+        Self { kind, report }
+    }
+}
+
+/// Derives the kind from the first error in the chain we know how to categorize
+fn classify(report: &eyre::Report) -> ErrorKind {
+    report
+        .chain()
+        .find_map(|e| {
+            if let Some(error) = e.downcast_ref::<OgError>() {
+                Some(error.kind)
+            } else if e.is::<bollard::errors::Error>() {
+                Some(ErrorKind::Docker)
+            } else if e.is::<reqwest::Error>() {
+                Some(ErrorKind::Network)
+            } else if e.is::<keyring::Error>() {
+                Some(ErrorKind::Auth)
+            } else if e.is::<figment::Error>() {
+                Some(ErrorKind::Config)
+            } else if let Some(dialoguer::Error::IO(error)) = e.downcast_ref::<dialoguer::Error>() {
+                (error.kind() == io::ErrorKind::Interrupted).then_some(ErrorKind::UserAbort)
+            } else {
+                None
+            }
+        })
+        .unwrap_or(ErrorKind::Other)
+}
+
+pub trait ResultExt<T> {
+    /// Assigns a kind to the error, replacing the one it would be classified as
+    fn error_kind(self, kind: ErrorKind) -> Result<T>;
+}
+
+impl<T, E> ResultExt<T> for std::result::Result<T, E>
+where
+    E: Into<eyre::Report>,
+{
+    fn error_kind(self, kind: ErrorKind) -> Result<T> {
+        self.map_err(|e| OgError::new(kind, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eyre::WrapErr;
+
+    #[test]
+    fn unknown_errors_are_other() {
+        let error = OgError::from(eyre::eyre!("something went wrong"));
+
+        assert_eq!(error.kind(), ErrorKind::Other);
+        assert_eq!(error.exit_code(), 1);
+    }
+
+    #[test]
+    fn kind_survives_wrapping() {
+        let report = Err::<(), _>(OgError::msg(ErrorKind::Auth, "token expired"))
+            .wrap_err("Unable to sync clusters")
+            .unwrap_err();
+        let error = OgError::from(report);
+
+        assert_eq!(error.kind(), ErrorKind::Auth);
+        assert_eq!(error.to_string(), "Unable to sync clusters");
+    }
+
+    #[test]
+    fn interrupted_prompts_are_user_aborts() {
+        let prompt_error = dialoguer::Error::IO(io::Error::from(io::ErrorKind::Interrupted));
+        let error = OgError::from(eyre::Report::new(prompt_error));
+
+        assert_eq!(error.kind(), ErrorKind::UserAbort);
+        assert_eq!(error.exit_code(), 130);
+    }
+
+    #[test]
+    fn explicit_kind_overrides_classification() {
+        let error = Err::<(), _>(eyre::eyre!("docker compose failed"))
+            .error_kind(ErrorKind::Docker)
+            .unwrap_err();
+
+        assert_eq!(error.exit_code(), 6);
+    }
+}
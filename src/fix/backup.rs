@@ -0,0 +1,272 @@
+use eyre::{Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+const BACKUP_PREFIX: &str = "fix-";
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    entries: Vec<BackupEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BackupEntry {
+    original: PathBuf,
+    backup: String,
+}
+
+/// Timestamped directory the files removed by fix-beta are moved to
+pub struct Backup {
+    backups_dir: PathBuf,
+    /// Created by the first move, so runs that remove nothing leave no backup behind
+    dir: Option<PathBuf>,
+    manifest: Manifest,
+}
+
+impl Backup {
+    pub fn new(backups_dir: &Path) -> Backup {
+        Backup {
+            backups_dir: backups_dir.to_path_buf(),
+            dir: None,
+            manifest: Manifest::default(),
+        }
+    }
+
+    /// The most recent backup that holds any files, if there is one
+    pub fn latest(backups_dir: &Path) -> Result<Option<Backup>> {
+        let Ok(entries) = fs::read_dir(backups_dir) else {
+            return Ok(None);
+        };
+
+        let mut backups: Vec<(u128, PathBuf)> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e.file_name().to_string_lossy().to_string();
+                let timestamp = name.strip_prefix(BACKUP_PREFIX)?.parse::<u128>().ok()?;
+                Some((timestamp, e.path()))
+            })
+            .collect();
+        backups.sort_by_key(|(timestamp, _)| std::cmp::Reverse(*timestamp));
+
+        for (_, dir) in backups {
+            let manifest_file = File::open(dir.join(MANIFEST_FILE))
+                .with_context(|| format!("Backup {} has no manifest", dir.display()))?;
+            let manifest: Manifest = serde_json::from_reader(manifest_file)
+                .with_context(|| format!("Backup {} is corrupt", dir.display()))?;
+            if !manifest.entries.is_empty() {
+                return Ok(Some(Backup {
+                    backups_dir: backups_dir.to_path_buf(),
+                    dir: Some(dir),
+                    manifest,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    /// Creates the backup directory, named after the current time in nanoseconds and unique even for concurrent runs
+    fn create_dir(&mut self) -> Result<PathBuf> {
+        if let Some(dir) = &self.dir {
+            return Ok(dir.clone());
+        }
+
+        fs::create_dir_all(&self.backups_dir).with_context(|| {
+            format!(
+                "Could not create backup directory {}",
+                self.backups_dir.display()
+            )
+        })?;
+        let mut timestamp = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)?
+            .as_nanos();
+        let dir = loop {
+            let dir = self.backups_dir.join(format!("{BACKUP_PREFIX}{timestamp}"));
+            match fs::create_dir(&dir) {
+                Ok(()) => break dir,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => timestamp += 1,
+                Err(e) => Err(e).with_context(|| {
+                    format!("Could not create backup directory {}", dir.display())
+                })?,
+            }
+        };
+
+        info!("Removed files are moved to {}", dir.display());
+        self.dir = Some(dir.clone());
+        self.save()?;
+
+        Ok(dir)
+    }
+
+    /// Moves a file or directory into the backup, returns false if it doesn't exist
+    pub fn move_in(&mut self, path: &Path) -> Result<bool> {
+        if fs::symlink_metadata(path).is_err() {
+            return Ok(false);
+        }
+
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let dir = self.create_dir()?;
+        let backup = format!("{}-{}", self.manifest.entries.len(), file_name);
+        move_path(path, &dir.join(&backup))
+            .with_context(|| format!("Could not back up {}", path.display()))?;
+
+        self.manifest.entries.push(BackupEntry {
+            original: path.to_path_buf(),
+            backup,
+        });
+        self.save()?;
+
+        Ok(true)
+    }
+
+    /// Moves everything back to its original location and deletes the backup
+    pub fn restore(self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+
+        for entry in self.manifest.entries.iter().rev() {
+            if fs::symlink_metadata(&entry.original).is_ok() {
+                remove_path(&entry.original)?;
+            }
+            if let Some(parent) = entry.original.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            move_path(&dir.join(&entry.backup), &entry.original)
+                .with_context(|| format!("Could not restore {}", entry.original.display()))?;
+            info!("Restored {}", entry.original.display());
+        }
+
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+
+    fn save(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        let manifest_file = File::create(dir.join(MANIFEST_FILE))?;
+        serde_json::to_writer_pretty(manifest_file, &self.manifest)?;
+
+        Ok(())
+    }
+}
+
+pub fn remove_path(path: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    // Renaming fails across file systems, e.g. with a separately mounted home
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_path(from, to)?;
+    remove_path(from)
+}
+
+fn copy_path(from: &Path, to: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    if metadata.is_symlink() {
+        copy_symlink(from, to)?;
+    } else if metadata.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(target_family = "unix")]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)?;
+    Ok(())
+}
+
+#[cfg(target_family = "windows")]
+fn copy_symlink(from: &Path, to: &Path) -> Result<()> {
+    fs::copy(from, to)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_and_restore() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        let backups_dir = home.join(".og-cli/backups");
+        fs::write(home.join(".dgrc"), "token: 123").unwrap();
+        fs::create_dir_all(home.join(".dg-cli/cache")).unwrap();
+
+        let mut backup = Backup::new(&backups_dir);
+        assert!(backup.move_in(&home.join(".dgrc")).unwrap());
+        assert!(backup.move_in(&home.join(".dg-cli")).unwrap());
+        assert!(!backup.move_in(&home.join(".missing")).unwrap());
+        assert!(!home.join(".dgrc").exists());
+        assert!(!home.join(".dg-cli").exists());
+
+        fs::write(home.join(".dgrc"), "reinstalled").unwrap();
+        let latest = Backup::latest(&backups_dir).unwrap().unwrap();
+        assert_eq!(latest.dir(), backup.dir());
+        latest.restore().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(home.join(".dgrc")).unwrap(),
+            "token: 123"
+        );
+        assert!(home.join(".dg-cli/cache").is_dir());
+        assert!(Backup::latest(&backups_dir).unwrap().is_none());
+    }
+
+    #[test]
+    fn rollback_skips_runs_that_removed_nothing() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        let backups_dir = home.join(".og-cli/backups");
+        fs::write(home.join(".dgrc"), "token: 123").unwrap();
+        fs::write(home.join(".dg-cli"), "cache").unwrap();
+
+        let mut failed = Backup::new(&backups_dir);
+        failed.move_in(&home.join(".dgrc")).unwrap();
+        let mut rerun = Backup::new(&backups_dir);
+        assert!(!rerun.move_in(&home.join(".dgrc")).unwrap());
+        assert_eq!(rerun.dir(), None);
+        let mut concurrent = Backup::new(&backups_dir);
+        concurrent.move_in(&home.join(".dg-cli")).unwrap();
+        concurrent.restore().unwrap();
+
+        let latest = Backup::latest(&backups_dir).unwrap().unwrap();
+        assert_eq!(latest.dir(), failed.dir());
+    }
+}
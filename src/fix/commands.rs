@@ -0,0 +1,12 @@
+use clap::Args;
+
+/// Recover the DG CLI (macOS and Linux)
+#[derive(Args, Debug)]
+pub struct FixCommand {
+    /// Only list the paths that would be removed and the commands that would run
+    #[arg(short, long)]
+    pub dry_run: bool,
+    /// Restore the files removed by the last run, e.g. after a failed reinstallation
+    #[arg(long, conflicts_with = "dry_run")]
+    pub rollback: bool,
+}
@@ -0,0 +1,232 @@
+use colored::Colorize;
+use eyre::{Context, ContextCompat, Result};
+use homedir::get_my_home;
+use std::{
+    env, fs,
+    process::{Command, Stdio},
+    sync::OnceLock,
+};
+
+use super::{FixCommand, FixPlugin};
+use crate::{
+    dg::{dg_command, dg_path, DG_CLI_USER_TYPE},
+    doctor::{DoctorFailure, DoctorSuccess},
+    plugin::Plugin,
+};
+
+const PLUGIN_NAME: &str = "DG CLI";
+const MIN_PYTHON_VERSION: (u32, u32) = (3, 9);
+const MIN_PIPX_VERSION: (u32, u32) = (1, 0);
+
+impl FixPlugin {
+    fn is_dg_cli_responding(&self) -> Result<DoctorSuccess, DoctorFailure> {
+        let output = dg_command()
+            .arg("--version")
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|error| {
+                failure(format!(
+                    "{} '{}': {}",
+                    "Unable to run the DG CLI, is it on the PATH?".red(),
+                    dg_path(),
+                    error.to_string().yellow()
+                ))
+            })?;
+
+        if !output.status.success() {
+            return Err(failure(format!(
+                "{}: {}",
+                format!("dg --version failed with {}", output.status).red(),
+                String::from_utf8_lossy(&output.stderr).trim().yellow()
+            )));
+        }
+
+        Ok(success(format!(
+            "{} {}",
+            "dg responds with version".green(),
+            String::from_utf8_lossy(&output.stdout).trim()
+        )))
+    }
+
+    fn is_dgrc_valid(&self) -> Result<DoctorSuccess, DoctorFailure> {
+        if let Err(error) = read_dgrc() {
+            return Err(failure(format!(
+                "{}: {}",
+                "Error while parsing ~/.dgrc".red(),
+                format!("{error:#}").yellow()
+            )));
+        }
+
+        Ok(success(format!("{}", "~/.dgrc is valid".green())))
+    }
+
+    fn is_python_adequate(&self) -> Result<DoctorSuccess, DoctorFailure> {
+        check_tool_version("python3", MIN_PYTHON_VERSION)
+    }
+
+    fn is_pipx_adequate(&self) -> Result<DoctorSuccess, DoctorFailure> {
+        check_tool_version("pipx", MIN_PIPX_VERSION)
+    }
+
+    fn is_user_type_handled(&self) -> Result<DoctorSuccess, DoctorFailure> {
+        // Forwarded commands run without prompting for the user type, which needs stdin
+        let status = dg_command()
+            .arg("--help")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        if !status.is_ok_and(|s| s.success()) {
+            return Err(failure(format!(
+                "{}",
+                format!("dg fails with DG_CLI_USER_TYPE={DG_CLI_USER_TYPE} and no input").red()
+            )));
+        }
+
+        let mut message = format!(
+            "{}",
+            format!("dg accepts DG_CLI_USER_TYPE={DG_CLI_USER_TYPE}").green()
+        );
+        if let Ok(user_type) = env::var("DG_CLI_USER_TYPE") {
+            if user_type != DG_CLI_USER_TYPE {
+                message.push_str(&format!(
+                    " ({})",
+                    format!("og overrides DG_CLI_USER_TYPE={user_type} from your environment")
+                        .yellow()
+                ));
+            }
+        }
+
+        Ok(success(message))
+    }
+
+    fn apply_fix_beta() -> Result<(), String> {
+        // Every failed check offers the same recovery, it only has to run once
+        static FIX_RESULT: OnceLock<Result<(), String>> = OnceLock::new();
+
+        FIX_RESULT
+            .get_or_init(|| {
+                tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(FixPlugin::run(FixCommand {
+                        dry_run: false,
+                        rollback: false,
+                    }))
+                })
+                .map_err(|error| format!("{}: {error}", "Unable to recover the DG CLI".red()))
+            })
+            .clone()
+    }
+}
+
+impl Plugin for FixPlugin {
+    fn doctor(&self) -> Vec<Result<DoctorSuccess, DoctorFailure>> {
+        vec![
+            self.is_dg_cli_responding(),
+            self.is_dgrc_valid(),
+            self.is_python_adequate(),
+            self.is_pipx_adequate(),
+            self.is_user_type_handled(),
+        ]
+    }
+}
+
+fn success(message: String) -> DoctorSuccess {
+    DoctorSuccess {
+        message,
+        plugin: PLUGIN_NAME.to_string(),
+    }
+}
+
+fn failure(message: String) -> DoctorFailure {
+    DoctorFailure {
+        message,
+        plugin: PLUGIN_NAME.to_string(),
+        fix: Some(Box::new(FixPlugin::apply_fix_beta)),
+    }
+}
+
+fn read_dgrc() -> Result<()> {
+    let dgrc = get_my_home()?
+        .context("Could not get home directory")?
+        .join(".dgrc");
+    let content =
+        fs::read_to_string(&dgrc).with_context(|| format!("Could not read {}", dgrc.display()))?;
+
+    parse_dgrc(&content)
+}
+
+fn parse_dgrc(content: &str) -> Result<()> {
+    let dgrc: serde_yaml::Value = serde_yaml::from_str(content)?;
+    dgrc.as_mapping().context("expected key value pairs")?;
+
+    Ok(())
+}
+
+fn check_tool_version(tool: &str, min_version: (u32, u32)) -> Result<DoctorSuccess, DoctorFailure> {
+    let output = Command::new(tool)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|_| failure(format!("{}", format!("{tool} is not installed").red())))?;
+
+    // Python 2 and older pipx versions print the version to stderr
+    let version_output = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let (major, minor) = parse_version(&version_output).ok_or_else(|| {
+        failure(format!(
+            "{}: {}",
+            format!("Unable to determine the {tool} version").red(),
+            version_output.trim().yellow()
+        ))
+    })?;
+
+    if (major, minor) < min_version {
+        return Err(failure(format!(
+            "{}",
+            format!(
+                "{tool} {major}.{minor} is too old, at least {}.{} is required",
+                min_version.0, min_version.1
+            )
+            .red()
+        )));
+    }
+
+    Ok(success(format!(
+        "{}",
+        format!("{tool} {major}.{minor} is installed").green()
+    )))
+}
+
+/// Major and minor version of outputs like `Python 3.11.4` or `1.4.3`
+fn parse_version(output: &str) -> Option<(u32, u32)> {
+    let version = output
+        .split_whitespace()
+        .find(|w| w.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = version.split('.').map(|p| p.parse::<u32>());
+
+    Some((parts.next()?.ok()?, parts.next()?.ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tool_versions() {
+        assert_eq!(parse_version("Python 3.11.4\n"), Some((3, 11)));
+        assert_eq!(parse_version("1.4.3\n"), Some((1, 4)));
+        assert_eq!(parse_version("command not found"), None);
+    }
+
+    #[test]
+    fn parses_dgrc() {
+        assert!(parse_dgrc("token: abc\nenvironment: test\n").is_ok());
+        assert!(parse_dgrc("{\"token\": \"abc\"}").is_ok());
+        assert!(parse_dgrc("token: [abc").is_err());
+        assert!(parse_dgrc("just a string").is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use eyre::{Context, ContextCompat};
+use homedir::get_my_home;
+use log::{error, info, warn};
+use std::{
+    env,
+    ffi::OsString,
+    fmt::{Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use which::which;
+
+use super::{
+    backup::Backup,
+    installer::{download_installer, installer_url, run_installer},
+    FixCommand,
+};
+use crate::{
+    config::{get_config, og_dir},
+    dg::dg_path,
+    error::{self, ErrorKind, OgError, ResultExt},
+    prompt,
+};
+
+pub struct FixPlugin;
+
+impl FixPlugin {
+    pub async fn run(cli: FixCommand) -> error::Result<()> {
+        let backups_dir = og_dir()?.join("backups");
+
+        if cli.rollback {
+            return Ok(rollback(&backups_dir)?);
+        }
+
+        let steps = plan(&home_dir()?)?;
+
+        if cli.dry_run {
+            info!("Recovering the DG CLI would:");
+            steps.iter().for_each(|s| info!("  {s}"));
+            return Ok(());
+        }
+
+        Ok(execute(&steps, &backups_dir).await?)
+    }
+}
+
+enum Step {
+    /// Moves the path to the backup
+    Remove(PathBuf),
+    Run(CommandStep),
+    InstallDgCli,
+    VerifyDgCli,
+}
+
+struct CommandStep {
+    program: String,
+    args: Vec<String>,
+    ignore_failure: bool,
+}
+
+impl CommandStep {
+    fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            ignore_failure: false,
+        }
+    }
+
+    fn sudo(args: &[&str]) -> Self {
+        // Without a terminal sudo must not wait for a password
+        if prompt::is_interactive() {
+            Self::new("sudo", args)
+        } else {
+            Self::new("sudo", &[&["-n"], args].concat())
+        }
+    }
+
+    fn ignore_failure(mut self) -> Self {
+        self.ignore_failure = true;
+        self
+    }
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Remove(path) => write!(f, "move {} to the backup", path.display()),
+            Step::Run(command) => {
+                write!(f, "run {} {}", command.program, command.args.join(" "))?;
+                if command.ignore_failure {
+                    write!(f, " (failures are ignored)")?;
+                }
+                Ok(())
+            }
+            Step::InstallDgCli => write!(
+                f,
+                "download {}, verify its SHA-256 and run it with python3",
+                installer_url()
+            ),
+            Step::VerifyDgCli => write!(f, "run {} --version", dg_path()),
+        }
+    }
+}
+
+fn plan(home: &Path) -> eyre::Result<Vec<Step>> {
+    plan_with(home, env::consts::OS, |program| which(program).is_ok())
+}
+
+fn plan_with(home: &Path, os: &str, installed: impl Fn(&str) -> bool) -> eyre::Result<Vec<Step>> {
+    let mut steps = vec![
+        Step::Remove(home.join(".dgrc")),
+        Step::Remove(home.join(".dg-cli")),
+        Step::Remove(home.join(".local/bin/dg")),
+    ];
+
+    match os {
+        "macos" => {
+            steps.push(Step::Remove(home.join(".local/pipx")));
+            steps.push(Step::Run(
+                CommandStep::new("brew", &["uninstall", "pipx"]).ignore_failure(),
+            ));
+            steps.push(Step::Run(CommandStep::new("brew", &["install", "pipx"])));
+        }
+        "linux" => {
+            steps.extend(dg_pipx_venvs(home).into_iter().map(Step::Remove));
+            steps.extend(install_pipx_linux(installed)?.map(Step::Run));
+        }
+        os => Err(OgError::msg(
+            ErrorKind::Other,
+            format!("Recovering the DG CLI is not supported on {os}"),
+        ))?,
+    }
+
+    steps.push(Step::InstallDgCli);
+    steps.push(Step::VerifyDgCli);
+
+    Ok(steps)
+}
+
+async fn execute(steps: &[Step], backups_dir: &Path) -> eyre::Result<()> {
+    info!("Reinstalling Dg Cli");
+    let mut backup = Backup::new(backups_dir);
+
+    for step in steps {
+        if let Err(error) = execute_step(step, &mut backup).await {
+            error!("Recovering the DG CLI failed at: {step}");
+            error!("Restore the removed files with: og fix-beta --rollback");
+            return Err(error);
+        }
+    }
+
+    if let Some(dir) = backup.dir() {
+        info!(
+            "Removed files are kept in {}, restore them with: og fix-beta --rollback",
+            dir.display()
+        );
+    }
+
+    Ok(())
+}
+
+async fn execute_step(step: &Step, backup: &mut Backup) -> eyre::Result<()> {
+    match step {
+        Step::Remove(path) => {
+            if backup.move_in(path)? {
+                info!("Removed {}", path.display());
+            }
+        }
+        Step::Run(command) => {
+            let result = run_command(command);
+            if command.ignore_failure {
+                if let Err(error) = result {
+                    warn!("{error}");
+                }
+            } else {
+                result?;
+            }
+        }
+        Step::InstallDgCli => install_dg_cli().await?,
+        Step::VerifyDgCli => verify_dg_cli()?,
+    }
+
+    Ok(())
+}
+
+fn rollback(backups_dir: &Path) -> eyre::Result<()> {
+    let backup = Backup::latest(backups_dir)?
+        .context("There is no backup to restore")
+        .error_kind(ErrorKind::Usage)?;
+
+    if let Some(dir) = backup.dir() {
+        info!("Restoring backup {}", dir.display());
+    }
+    backup.restore()?;
+    info!("Backup restored, pipx installations are not rolled back");
+
+    Ok(())
+}
+
+async fn install_dg_cli() -> eyre::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let installer = download_installer(
+        installer_url(),
+        get_config().dg_cli_installer_sha256.as_deref(),
+        dir.path(),
+    )
+    .await?;
+
+    run_installer(&installer, path_with_user_bin()?)
+}
+
+fn verify_dg_cli() -> eyre::Result<()> {
+    let output = Command::new(dg_path())
+        .arg("--version")
+        .env("PATH", path_with_user_bin()?)
+        .output()
+        .context("dg command failed to start, is ~/.local/bin in your PATH?")
+        .error_kind(ErrorKind::ExternalTool)?;
+
+    if !output.status.success() {
+        Err(OgError::msg(
+            ErrorKind::ExternalTool,
+            format!(
+                "dg --version failed after reinstalling: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        ))?;
+    }
+
+    info!(
+        "DG CLI reinstalled successfully: {}",
+        String::from_utf8_lossy(&output.stdout).trim()
+    );
+
+    Ok(())
+}
+
+/// The pipx virtual environments of the DG CLI
+fn dg_pipx_venvs(home: &Path) -> Vec<PathBuf> {
+    pipx_venv_dirs(home)
+        .iter()
+        .filter_map(|d| fs::read_dir(d).ok())
+        .flatten()
+        .filter_map(|v| v.ok())
+        .filter(|v| v.file_name().to_string_lossy().starts_with("dg"))
+        .map(|v| v.path())
+        .collect()
+}
+
+/// Directories pipx keeps its virtual environments in, depending on version and PIPX_HOME
+fn pipx_venv_dirs(home: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![
+        home.join(".local/share/pipx/venvs"),
+        home.join(".local/pipx/venvs"),
+    ];
+    if let Some(pipx_home) = env::var_os("PIPX_HOME") {
+        dirs.push(PathBuf::from(pipx_home).join("venvs"));
+    }
+    dirs
+}
+
+/// Installs pipx with the system package manager or, as a fallback, for the current user
+fn install_pipx_linux(installed: impl Fn(&str) -> bool) -> eyre::Result<Option<CommandStep>> {
+    if installed("pipx") {
+        return Ok(None);
+    }
+
+    let step = if installed("apt-get") {
+        CommandStep::sudo(&[
+            "apt-get",
+            "install",
+            "-y",
+            "python3",
+            "python3-venv",
+            "pipx",
+        ])
+    } else if installed("dnf") {
+        CommandStep::sudo(&["dnf", "install", "-y", "python3", "pipx"])
+    } else {
+        if !installed("python3") {
+            Err(OgError::msg(
+                ErrorKind::ExternalTool,
+                "Neither apt-get nor dnf nor python3 found, please install python3 and pipx manually",
+            ))?;
+        }
+        CommandStep::new("python3", &["-m", "pip", "install", "--user", "pipx"])
+    };
+
+    Ok(Some(step))
+}
+
+fn run_command(command: &CommandStep) -> eyre::Result<()> {
+    info!("Running {} {}", command.program, command.args.join(" "));
+    let status = Command::new(&command.program)
+        .args(&command.args)
+        .status()
+        .with_context(|| format!("{} command failed to start", command.program))
+        .error_kind(ErrorKind::ExternalTool)?;
+
+    if !status.success() {
+        Err(OgError::msg(
+            ErrorKind::ExternalTool,
+            format!(
+                "{} {} failed with {status}",
+                command.program,
+                command.args.join(" ")
+            ),
+        ))?;
+    }
+
+    Ok(())
+}
+
+fn home_dir() -> eyre::Result<PathBuf> {
+    get_my_home()?.context("Could not get home directory")
+}
+
+/// PATH including ~/.local/bin, where pipx installs the DG CLI
+fn path_with_user_bin() -> eyre::Result<OsString> {
+    let user_bin = home_dir()?.join(".local/bin");
+    let paths = env::var_os("PATH").unwrap_or_default();
+
+    Ok(env::join_paths(
+        std::iter::once(user_bin).chain(env::split_paths(&paths)),
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_dg_pipx_venvs_only() {
+        let home = tempfile::tempdir().unwrap();
+        let home = home.path();
+        fs::create_dir_all(home.join(".local/share/pipx/venvs/dg-cli")).unwrap();
+        fs::create_dir_all(home.join(".local/share/pipx/venvs/black")).unwrap();
+
+        assert_eq!(
+            dg_pipx_venvs(home),
+            vec![home.join(".local/share/pipx/venvs/dg-cli")]
+        );
+    }
+
+    #[test]
+    fn plan_removes_dg_cli_files_first() {
+        let home = Path::new("/home/og");
+        let steps = plan_with(home, "macos", |_| false).unwrap();
+
+        assert_eq!(steps[0].to_string(), "move /home/og/.dgrc to the backup");
+        assert_eq!(steps[1].to_string(), "move /home/og/.dg-cli to the backup");
+        assert_eq!(
+            steps[2].to_string(),
+            "move /home/og/.local/bin/dg to the backup"
+        );
+        assert!(matches!(steps.last(), Some(Step::VerifyDgCli)));
+    }
+
+    #[test]
+    fn plan_installs_pipx_with_the_package_manager_found() {
+        let home = Path::new("/home/og");
+
+        let with_dnf = plan_with(home, "linux", |p| p == "dnf").unwrap();
+        let with_pipx = plan_with(home, "linux", |p| p == "pipx").unwrap();
+        let without_python = plan_with(home, "linux", |_| false).err().unwrap();
+
+        assert!(with_dnf
+            .iter()
+            .any(|s| matches!(s, Step::Run(c) if c.args.contains(&"dnf".to_string()))));
+        assert!(!with_pipx.iter().any(|s| matches!(s, Step::Run(_))));
+        assert_eq!(
+            OgError::from(without_python).kind(),
+            ErrorKind::ExternalTool
+        );
+        assert!(plan_with(home, "windows", |_| true).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use eyre::{Context, ContextCompat, Result};
+use log::{debug, info};
+use sha2::{Digest, Sha256};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    config::get_config,
+    error::{ErrorKind, OgError, ResultExt},
+};
+
+const DG_CLI_INSTALLER_URL: &str = "https://dgcli.platform.prod.int.devinite.com/install.py";
+
+/// The configured installer URL, defaults to the official DG CLI installer
+pub fn installer_url() -> &'static str {
+    get_config()
+        .dg_cli_installer_url
+        .as_deref()
+        .unwrap_or(DG_CLI_INSTALLER_URL)
+}
+
+/// Downloads the installer into `dir` and verifies it against the pinned or published SHA-256
+pub async fn download_installer(
+    url: &str,
+    pinned_sha256: Option<&str>,
+    dir: &Path,
+) -> Result<PathBuf> {
+    let client = reqwest::Client::new();
+
+    info!("Downloading DG CLI installer from {url}");
+    let installer = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await
+        .with_context(|| format!("Unable to download the DG CLI installer from {url}"))?;
+
+    let expected = match pinned_sha256 {
+        Some(sha256) => sha256.to_string(),
+        None => {
+            let checksum_url = format!("{url}.sha256");
+            let checksum = client
+                .get(&checksum_url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await
+                .with_context(|| {
+                    format!("Unable to download the installer checksum from {checksum_url}")
+                })?;
+            // Accept both a plain hash and the `sha256sum` format
+            checksum
+                .split_whitespace()
+                .next()
+                .with_context(|| format!("{checksum_url} is empty"))?
+                .to_string()
+        }
+    };
+
+    let actual = sha256_hex(&installer);
+    if !actual.eq_ignore_ascii_case(expected.trim()) {
+        Err(OgError::msg(
+            ErrorKind::Other,
+            format!(
+                "Checksum mismatch for the DG CLI installer, expected {expected} but got {actual}"
+            ),
+        ))?;
+    }
+    debug!("DG CLI installer has the expected SHA-256 {actual}");
+
+    let path = dir.join("install.py");
+    fs::write(&path, &installer)
+        .with_context(|| format!("Could not write installer to {}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Runs the installer with python3, its output is only shown if it fails
+pub fn run_installer(installer: &Path, path: OsString) -> Result<()> {
+    let output = Command::new("python3")
+        .arg(installer)
+        .env("PATH", path)
+        .output()
+        .context("python3 command failed to start")
+        .error_kind(ErrorKind::ExternalTool)?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    debug!("DG CLI installer output:\n{stdout}{stderr}");
+
+    if !output.status.success() {
+        Err(OgError::msg(
+            ErrorKind::ExternalTool,
+            format!(
+                "DG CLI installer failed with {}:\n{}{}",
+                output.status,
+                stdout.trim_end(),
+                stderr.trim_end()
+            ),
+        ))?;
+    }
+    info!("DG CLI installer finished");
+
+    Ok(())
+}
+
+fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    const INSTALLER: &str = "print('installed')\n";
+
+    /// Serves the installer and its checksum for the given number of requests
+    fn serve_installer(checksum: String, requests: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/install.py", listener.local_addr().unwrap());
+
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let request = BufReader::new(&stream)
+                    .lines()
+                    .map(|l| l.unwrap())
+                    .take_while(|l| !l.is_empty())
+                    .collect::<Vec<_>>();
+
+                let body = if request[0].contains("install.py.sha256") {
+                    format!("{checksum}  install.py\n")
+                } else {
+                    INSTALLER.to_string()
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+
+        url
+    }
+
+    #[test]
+    fn hashes_sha256() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[tokio::test]
+    async fn downloads_installer_with_published_checksum() {
+        let url = serve_installer(sha256_hex(INSTALLER.as_bytes()), 2);
+        let dir = tempfile::tempdir().unwrap();
+
+        let installer = download_installer(&url, None, dir.path()).await.unwrap();
+
+        assert_eq!(fs::read_to_string(installer).unwrap(), INSTALLER);
+    }
+
+    #[tokio::test]
+    async fn rejects_installer_with_wrong_checksum() {
+        let url = serve_installer(sha256_hex(b"tampered"), 2);
+        let dir = tempfile::tempdir().unwrap();
+
+        let result = download_installer(&url, None, dir.path()).await;
+
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Checksum mismatch"));
+        assert!(!dir.path().join("install.py").exists());
+    }
+
+    #[tokio::test]
+    async fn pinned_checksum_skips_published_one() {
+        let url = serve_installer(String::new(), 1);
+        let dir = tempfile::tempdir().unwrap();
+        let pinned = sha256_hex(INSTALLER.as_bytes());
+
+        assert!(download_installer(&url, Some(&pinned), dir.path())
+            .await
+            .is_ok());
+    }
+}
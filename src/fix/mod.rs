@@ -0,0 +1,8 @@
+mod backup;
+mod commands;
+mod doctor;
+mod fix;
+mod installer;
+
+pub use commands::FixCommand;
+pub use fix::FixPlugin;
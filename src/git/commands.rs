@@ -1,4 +1,5 @@
-use clap::{Args, Subcommand};
+use clap::{Args, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 /// Git helpers
 #[derive(Args, Debug)]
@@ -11,5 +12,18 @@ pub struct GitCommand {
 #[derive(Subcommand, Debug)]
 pub enum GitSubCommands {
     /// Setup your local git config to use ssh and a seperate .gitconfig for your work projects
-    Setup,
+    Setup {
+        /// Existing public keys to use instead of selecting them interactively
+        #[arg(short, long = "key", value_name = "PATH")]
+        keys: Vec<PathBuf>,
+        /// Platforms to create a new ssh key for if none exists (default: all)
+        #[arg(short, long = "create-for", value_name = "PLATFORM")]
+        platforms: Vec<GitPlatform>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum GitPlatform {
+    AzureDevops,
+    Github,
 }
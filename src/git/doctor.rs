@@ -17,7 +17,8 @@ impl Plugin for GitPlugin {
 
 fn apply_fix_config() -> Result<(), String> {
     let entry = "push.autoSetupRemote";
-    let mut config = git2::Config::open_default().expect("git config lookup failed!");
+    let mut config =
+        git2::Config::open_default().map_err(|_| "git config lookup failed".to_string())?;
 
     let result = config.set_bool(entry, true);
     match result {
@@ -29,7 +30,11 @@ fn apply_fix_config() -> Result<(), String> {
 impl GitPlugin {
     fn git_config_check() -> Result<DoctorSuccess, DoctorFailure> {
         let entry = "push.autoSetupRemote";
-        let config = git2::Config::open_default().expect("git config lookup failed!");
+        let config = git2::Config::open_default().map_err(|_| DoctorFailure {
+            message: "git config lookup failed".into(),
+            plugin: "git - config".into(),
+            fix: None,
+        })?;
         match config.get_bool(entry) {
             Ok(c) if c == true => Ok(DoctorSuccess {
                 message: format!("{} is configured correct with {} ", entry, c.to_string()),
@@ -1,57 +1,88 @@
-use dialoguer::MultiSelect;
-use eyre::{eyre, ContextCompat, Result};
+use eyre::{Context, ContextCompat, Result};
 use homedir::get_my_home;
 use log::info;
 use ssh_key::{rand_core::OsRng, Algorithm, LineEnding, PrivateKey, PublicKey};
-use std::{ffi::OsStr, process::Command};
-
-use super::commands::{GitCommand, GitSubCommands};
+use std::{
+    ffi::OsStr,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use super::commands::{GitCommand, GitPlatform, GitSubCommands};
+use crate::{
+    error::{self, ErrorKind, OgError, ResultExt},
+    prompt,
+};
 
 pub struct GitPlugin;
 
 impl GitPlugin {
-    pub fn run(cli: GitCommand) {
+    pub fn run(cli: GitCommand) -> error::Result<()> {
         match cli.command {
-            GitSubCommands::Setup => setup().unwrap(),
+            GitSubCommands::Setup { keys, platforms } => setup(keys, platforms)?,
         }
+
+        Ok(())
     }
 }
 
-fn setup() -> Result<()> {
-    let _ = ensure_ssh_keys()?;
+fn setup(keys: Vec<PathBuf>, platforms: Vec<GitPlatform>) -> Result<()> {
+    let keys = ensure_ssh_keys(keys, platforms)?;
 
-    add_keys_github()?;
+    add_keys_github(&keys)?;
 
     Ok(())
 }
 
-#[cfg(target_family = "unix")]
-fn add_keys_github() -> Result<()> {
-    Command::new("sh")
-        .arg("-c")
-        .arg("gh ssh-key add ~/.ssh/og-ssh.pub -t og")
-        .output()
-        .expect("failed to upload ssh key to github");
+/// Uploads the keys with the GitHub CLI, which reads each one from stdin
+fn add_keys_github(keys: &[PublicKey]) -> Result<()> {
+    for key in keys {
+        let mut gh = Command::new("gh")
+            .args(["ssh-key", "add", "-", "-t", "og"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("gh could not be started, is the GitHub CLI installed?")
+            .error_kind(ErrorKind::ExternalTool)?;
+        gh.stdin
+            .take()
+            .context("gh has no stdin")?
+            .write_all(key.to_openssh()?.as_bytes())?;
+        let output = gh.wait_with_output()?;
+
+        if !output.status.success() {
+            Err(OgError::msg(
+                ErrorKind::ExternalTool,
+                format!(
+                    "Failed to upload ssh key to GitHub: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ))?;
+        }
+    }
 
     Ok(())
 }
 
-#[cfg(target_family = "windows")]
-fn add_keys_github() -> Result<()> {
-    // Command::new("cmd")
-    //     .args(["/C", "gh ssh-key add ~/.ssh/og-ssh.pub -t og"])
-    //     .output()
-    //     .expect("failed to execute process")
-    todo!("implement ssh key upload on windows")
-}
-
 #[cfg(target_family = "unix")]
-fn ensure_ssh_keys() -> Result<Vec<PublicKey>> {
+fn ensure_ssh_keys(keys: Vec<PathBuf>, platforms: Vec<GitPlatform>) -> Result<Vec<PublicKey>> {
+    if !keys.is_empty() {
+        return keys
+            .iter()
+            .map(|k| {
+                PublicKey::read_openssh_file(k)
+                    .with_context(|| format!("{} is not a valid public key", k.display()))
+            })
+            .collect();
+    }
+
     let ssh_dir = get_my_home()?
         .context("Could not get home directory")?
         .join(".ssh2"); // move this check to the doctor
 
-    let public_keys: Vec<PublicKey> = ssh_dir
+    let found: Vec<(PathBuf, PublicKey)> = ssh_dir
         .read_dir()?
         .filter_map(|r| r.ok())
         .map(|r| {
@@ -64,41 +95,58 @@ fn ensure_ssh_keys() -> Result<Vec<PublicKey>> {
             )
         })
         .filter(|(extension, _)| extension.as_ref().map(|c| c == "pub").unwrap_or(false))
-        .map(|(_, e)| PublicKey::read_openssh_file(e.path().as_path()))
-        .filter_map(|p| p.ok())
+        .filter_map(|(_, e)| Some((e.path(), PublicKey::read_openssh_file(&e.path()).ok()?)))
         .collect();
 
-    if public_keys.is_empty() {
+    if found.is_empty() {
+        let all_platforms = [GitPlatform::AzureDevops, GitPlatform::Github];
         let creation_options = &["Azure Devops", "Github"];
         let defaults = &[true, true];
-        let selection = MultiSelect::new()
-            .with_prompt("Should OG create ssh keys for you?")
-            .items(&creation_options[..])
-            .defaults(&defaults[..])
-            .interact()
-            .unwrap();
+        let selection: Vec<GitPlatform> = if !platforms.is_empty() {
+            platforms
+        } else {
+            prompt::multi_select(
+                "Should OG create ssh keys for you?",
+                creation_options,
+                defaults,
+                "--create-for <PLATFORM>",
+            )?
+            .into_iter()
+            .map(|i| all_platforms[i].clone())
+            .collect()
+        };
 
         if selection.is_empty() {
-            return Err(eyre!("No git platforms were selected. Abort key creation"));
+            Err(OgError::msg(
+                ErrorKind::UserAbort,
+                "No git platforms were selected. Abort key creation",
+            ))?;
         }
 
         let private_key_ed = PrivateKey::random(&mut OsRng, Algorithm::Ed25519)?;
 
         private_key_ed
             .write_openssh_file(ssh_dir.join("og-ssh").as_path(), LineEnding::LF)
-            .expect("key could not be created");
+            .context("key could not be created")?;
         private_key_ed
             .public_key()
             .write_openssh_file(ssh_dir.join("og-ssh.pub").as_path())
-            .expect("key could not be created");
+            .context("key could not be created")?;
 
         return Ok(vec![private_key_ed.public_key().clone()]);
     } else {
-        let selection = MultiSelect::new()
-            .with_prompt("Select ssh keys to use")
-            .items(&public_keys)
-            .interact()
-            .unwrap();
+        // The key og created is the default, e.g. under --yes
+        let defaults: Vec<bool> = found
+            .iter()
+            .map(|(path, _)| path.ends_with("og-ssh.pub"))
+            .collect();
+        let public_keys: Vec<PublicKey> = found.into_iter().map(|(_, key)| key).collect();
+        let selection = prompt::multi_select(
+            "Select ssh keys to use",
+            &public_keys,
+            &defaults,
+            "--key <PATH>",
+        )?;
         info!("You chose:");
 
         return Ok(selection.iter().map(|r| public_keys[*r].clone()).collect());
@@ -106,7 +154,7 @@ fn ensure_ssh_keys() -> Result<Vec<PublicKey>> {
 }
 
 #[cfg(target_family = "windows")]
-fn ensure_ssh_keys() -> Result<Vec<PublicKey>> {
+fn ensure_ssh_keys(_: Vec<PathBuf>, _: Vec<GitPlatform>) -> Result<Vec<PublicKey>> {
     Ok(vec![])
 }
 
@@ -1,11 +1,12 @@
 use arboard::Clipboard;
 use clap::{Args, Subcommand};
-use eyre::Result;
+use eyre::WrapErr;
 use gid::{Gid, Type};
 use log::info;
 
 use crate::{
     doctor::{DoctorFailure, DoctorSuccess},
+    error::Result,
     plugin::Plugin,
 };
 
@@ -41,17 +42,21 @@ pub struct GraphQlPlugin;
 
 impl GraphQlPlugin {
     pub fn run(cli: GraphQlCommand) -> Result<()> {
-        let mut clipboard = Clipboard::new()?;
+        let mut clipboard = Clipboard::new().wrap_err("Unable to access the clipboard")?;
         match cli.command {
             GraphQlSubcommands::Encode { name, id, id_type } => {
                 let gid = Gid::new(name, id, id_type);
                 let encoded_gid = gid.to_string();
-                clipboard.set_text(&encoded_gid)?;
+                clipboard
+                    .set_text(&encoded_gid)
+                    .wrap_err("Unable to copy to the clipboard")?;
                 info!("{encoded_gid}")
             }
             GraphQlSubcommands::Decode { id } => {
                 let gid = Gid::try_from(id)?;
-                clipboard.set_text(&gid.id)?;
+                clipboard
+                    .set_text(&gid.id)
+                    .wrap_err("Unable to copy to the clipboard")?;
                 info!("{gid:#?}");
             }
         }
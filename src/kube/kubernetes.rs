@@ -1,11 +1,12 @@
 use clap::{Args, Subcommand};
 use colored::Colorize;
-use dialoguer::{Confirm, MultiSelect};
-use eyre::Context;
+use dialoguer::MultiSelect;
+use eyre::{Context, ContextCompat};
 use log::{error, info, warn};
 use std::fmt::{Display, Formatter};
 
 use super::{kube_config, kube_config::*, rancher::*};
+use crate::{error, prompt};
 
 pub const KEYRING_SERVICE_ID: &str = "dg_cli_plugin_kube";
 pub const KEYRING_KEY: &str = "rancher_token";
@@ -29,6 +30,9 @@ pub enum KubernetesSubcommands {
         /// Do not create a new Rancher API token
         #[arg(short = 'T', long)]
         no_rancher_token: bool,
+        /// Store this existing Rancher API token instead of asking for one, prefer the environment variable to keep it out of the shell history
+        #[arg(long, env = "OG_RANCHER_TOKEN", hide_env_values = true)]
+        rancher_token: Option<String>,
         /// Omits doing a backup of your kubeconfig
         #[arg(short = 'B', long)]
         no_backup: bool,
@@ -38,30 +42,89 @@ pub enum KubernetesSubcommands {
         /// Omits doing a backup of your kubeconfig
         #[arg(short = 'B', long)]
         no_backup: bool,
+        /// Sync only these clusters instead of selecting them interactively
+        #[arg(short, long = "cluster", value_name = "NAME")]
+        clusters: Vec<String>,
+        /// Sync all clusters instead of selecting them interactively
+        #[arg(short, long, conflicts_with = "clusters")]
+        all: bool,
     },
     /// Cleanup (delete) local kubeconfig
     Cleanup {
         /// Omits doing a backup of your kubeconfig
         #[arg(short = 'B', long)]
         no_backup: bool,
+        /// Delete only these clusters instead of selecting them interactively
+        #[arg(short, long = "cluster", value_name = "NAME")]
+        clusters: Vec<String>,
+        /// Delete all clusters instead of selecting them interactively
+        #[arg(short, long, conflicts_with = "clusters")]
+        all: bool,
     },
 }
 
+/// Clusters chosen by flags, `None` if they should be selected interactively
+fn cluster_selection(clusters: Vec<String>, all: bool) -> Option<ClusterSelection> {
+    if all {
+        Some(ClusterSelection::All)
+    } else if !clusters.is_empty() {
+        Some(ClusterSelection::Named(clusters))
+    } else {
+        None
+    }
+}
+
+enum ClusterSelection {
+    All,
+    Named(Vec<String>),
+}
+
+impl ClusterSelection {
+    /// Indices of the selected clusters, names match with or without the Rancher suffix
+    fn select(&self, cluster_names: &[String]) -> eyre::Result<Vec<usize>> {
+        match self {
+            ClusterSelection::All => Ok((0..cluster_names.len()).collect()),
+            ClusterSelection::Named(selected) => selected
+                .iter()
+                .map(|s| {
+                    cluster_names
+                        .iter()
+                        .position(|n| {
+                            n == s
+                                || (n.starts_with(s.as_str())
+                                    && n.len() == s.len() + RANCHER_CLUSTER_SUFFIX_LENGTH)
+                        })
+                        .with_context(|| format!("Cluster {s} not found"))
+                })
+                .collect(),
+        }
+    }
+}
+
 pub struct KubernetesPlugin;
 
 impl KubernetesPlugin {
-    pub async fn run(cli: KubernetesCommand) -> eyre::Result<()> {
+    pub async fn run(cli: KubernetesCommand) -> error::Result<()> {
         match cli.command {
-            KubernetesSubcommands::Sync { no_backup } => run_sync(!no_backup)
+            KubernetesSubcommands::Sync {
+                no_backup,
+                clusters,
+                all,
+            } => run_sync(!no_backup, cluster_selection(clusters, all))
                 .await
                 .context("Unable to sync clusters due to errors")?,
-            KubernetesSubcommands::Cleanup { no_backup } => run_cleanup(!no_backup)
+            KubernetesSubcommands::Cleanup {
+                no_backup,
+                clusters,
+                all,
+            } => run_cleanup(!no_backup, cluster_selection(clusters, all))
                 .context("Unable to cleanup local kubeconfig due to errors")?,
             KubernetesSubcommands::Init {
                 no_kubeconfig,
                 no_rancher_token,
+                rancher_token,
                 no_backup,
-            } => run_init(!no_kubeconfig, !no_rancher_token, !no_backup)
+            } => run_init(!no_kubeconfig, !no_rancher_token, rancher_token, !no_backup)
                 .context("Unable to initialize your local environment for first use")?,
         }
 
@@ -91,6 +154,15 @@ struct ClusterSyncAction {
     action: SyncAction,
 }
 
+impl ClusterSyncAction {
+    fn cluster_fullname(&self) -> String {
+        self.rancher_cluster
+            .as_ref()
+            .or(self.local_cluster.as_ref())
+            .map_or_else(String::new, get_cluster_fullname)
+    }
+}
+
 impl Display for ClusterSyncAction {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -107,11 +179,14 @@ impl Display for ClusterSyncAction {
     }
 }
 
-async fn run_sync(kubeconfig_backup: bool) -> eyre::Result<()> {
+async fn run_sync(
+    kubeconfig_backup: bool,
+    selection: Option<ClusterSelection>,
+) -> eyre::Result<()> {
     print_credential_store_warning();
 
     let rancher_token = get_rancher_token()?;
-    let rancher_clusters = get_rancher_clusters(&rancher_token).await;
+    let rancher_clusters = get_rancher_clusters(&rancher_token).await?;
     let local_clusters = get_local_clusters()?;
 
     if rancher_clusters.is_empty() {
@@ -138,12 +213,23 @@ async fn run_sync(kubeconfig_backup: bool) -> eyre::Result<()> {
         return Ok(());
     }
 
-    let selected_actions = MultiSelect::new()
-        .with_prompt("Select ([SPACE]) the clusters to sync ([Ctrl + C] to abort)")
-        .items(&cluster_synch_actions)
-        .interact()
-        .unwrap();
-    info!("");
+    let selected_actions = match selection {
+        Some(selection) => selection.select(
+            &cluster_synch_actions
+                .iter()
+                .map(|a| a.cluster_fullname())
+                .collect::<Vec<_>>(),
+        )?,
+        None => {
+            prompt::ensure_interactive("--cluster <NAME> or --all")?;
+            let selected_actions = MultiSelect::new()
+                .with_prompt("Select ([SPACE]) the clusters to sync ([Ctrl + C] to abort)")
+                .items(&cluster_synch_actions)
+                .interact()?;
+            info!("");
+            selected_actions
+        }
+    };
 
     if selected_actions.is_empty() {
         error!("{}", "No sync action selected.".red());
@@ -161,7 +247,7 @@ async fn run_sync(kubeconfig_backup: bool) -> eyre::Result<()> {
             SyncAction::Create => {
                 create_kubeconfig_entry(
                     &mut kubeconfig,
-                    remote_cluster.as_ref().unwrap(),
+                    remote_cluster.as_ref().context("Rancher cluster missing")?,
                     &rancher_token,
                 )
                 .await?
@@ -169,15 +255,16 @@ async fn run_sync(kubeconfig_backup: bool) -> eyre::Result<()> {
             SyncAction::Update => {
                 update_kubeconfig_entry(
                     &mut kubeconfig,
-                    local_cluster.as_ref().unwrap(),
-                    remote_cluster.as_ref().unwrap(),
+                    local_cluster.as_ref().context("Local cluster missing")?,
+                    remote_cluster.as_ref().context("Rancher cluster missing")?,
                     &rancher_token,
                 )
                 .await?
             }
-            SyncAction::Delete => {
-                delete_kubeconfig_entry(&mut kubeconfig, local_cluster.as_ref().unwrap())?
-            }
+            SyncAction::Delete => delete_kubeconfig_entry(
+                &mut kubeconfig,
+                local_cluster.as_ref().context("Local cluster missing")?,
+            )?,
         }
     }
 
@@ -191,7 +278,7 @@ async fn run_sync(kubeconfig_backup: bool) -> eyre::Result<()> {
     Ok(())
 }
 
-fn run_cleanup(kubeconfig_backup: bool) -> eyre::Result<()> {
+fn run_cleanup(kubeconfig_backup: bool, selection: Option<ClusterSelection>) -> eyre::Result<()> {
     let mut kubeconfig = read_kubeconfig()?;
     let clusters: Vec<String> = kubeconfig
         .clusters
@@ -207,12 +294,18 @@ fn run_cleanup(kubeconfig_backup: bool) -> eyre::Result<()> {
         return Ok(());
     }
 
-    let mut selected_clusters = MultiSelect::new()
-        .with_prompt("Select ([SPACE]) the clusters to delete ([Ctrl + C] to abort)")
-        .items(&clusters)
-        .interact()
-        .unwrap();
-    info!("");
+    let mut selected_clusters = match selection {
+        Some(selection) => selection.select(&clusters)?,
+        None => {
+            prompt::ensure_interactive("--cluster <NAME> or --all")?;
+            let selected_clusters = MultiSelect::new()
+                .with_prompt("Select ([SPACE]) the clusters to delete ([Ctrl + C] to abort)")
+                .items(&clusters)
+                .interact()?;
+            info!("");
+            selected_clusters
+        }
+    };
 
     if selected_clusters.is_empty() {
         info!(
@@ -242,6 +335,7 @@ fn run_cleanup(kubeconfig_backup: bool) -> eyre::Result<()> {
 fn run_init(
     create_kubeconfig: bool,
     create_rancher_token: bool,
+    rancher_token: Option<String>,
     kubeconfig_backup: bool,
 ) -> eyre::Result<()> {
     if create_kubeconfig {
@@ -258,12 +352,19 @@ fn run_init(
     if create_rancher_token {
         print_credential_store_warning();
 
+        if let Some(token) = rancher_token {
+            return store_rancher_token(&token);
+        }
+
         let rancher_token_result = get_rancher_token();
         match rancher_token_result {
+            // Without prompts the existing token is kept, a new one couldn't be entered anyway
+            Ok(_) if !prompt::is_interactive() => info!(
+                "An existing Rancher API token has been found in the credential store, keeping it"
+            ),
             Ok(_) => {
                 warn!("{}", "An existing Rancher API token has been found in the credential store. Overwrite?".yellow());
-                let confirmed = Confirm::new().default(false).interact_opt().unwrap();
-                if let Some(true) = confirmed {
+                if prompt::confirm("Overwrite", false)? {
                     add_rancher_token()?;
                 }
             }
@@ -356,7 +457,10 @@ async fn create_kubeconfig_entry(
     rancher_token: &String,
 ) -> eyre::Result<()> {
     let name = &get_cluster_fullname(rancher_cluster);
-    let token_url = rancher_cluster.token_url.as_ref().expect("");
+    let token_url = rancher_cluster
+        .token_url
+        .as_ref()
+        .context("Rancher cluster has no kubeconfig URL")?;
     let rancher_kubeconfig = get_rancher_kubeconfig(token_url.to_string(), rancher_token).await?;
 
     kubeconfig.clusters.push(NamedCluster {
@@ -366,7 +470,7 @@ async fn create_kubeconfig_entry(
             server: rancher_kubeconfig
                 .clusters
                 .first()
-                .unwrap()
+                .context("Rancher kubeconfig contains no cluster")?
                 .cluster
                 .server
                 .to_string(),
@@ -389,11 +493,8 @@ async fn create_kubeconfig_entry(
                 rancher_kubeconfig
                     .users
                     .first()
-                    .unwrap()
-                    .user
-                    .token
-                    .as_ref()
-                    .unwrap()
+                    .and_then(|u| u.user.token.as_ref())
+                    .context("Rancher kubeconfig contains no user token")?
                     .to_string(),
             ),
             client_certificate_data: None,
@@ -410,18 +511,21 @@ async fn update_kubeconfig_entry(
     rancher_cluster: &Cluster,
     rancher_token: &String,
 ) -> eyre::Result<()> {
-    let token_url = rancher_cluster.token_url.as_ref().expect("");
+    let token_url = rancher_cluster
+        .token_url
+        .as_ref()
+        .context("Rancher cluster has no kubeconfig URL")?;
     let rancher_kubeconfig = get_rancher_kubeconfig(token_url.to_string(), rancher_token).await?;
 
     let cluster_pos = kubeconfig
         .clusters
         .iter()
         .position(|c| c.name == local_cluster.id)
-        .unwrap();
+        .context("Cluster not found in local kubeconfig")?;
     kubeconfig.clusters[cluster_pos].cluster.server = rancher_kubeconfig
         .clusters
         .first()
-        .unwrap()
+        .context("Rancher kubeconfig contains no cluster")?
         .cluster
         .server
         .to_string();
@@ -430,16 +534,13 @@ async fn update_kubeconfig_entry(
         .users
         .iter()
         .position(|u| u.name == local_cluster.id)
-        .unwrap();
+        .context("User not found in local kubeconfig")?;
     kubeconfig.users[user_pos].user.token = Some(
         rancher_kubeconfig
             .users
             .first()
-            .unwrap()
-            .user
-            .token
-            .as_ref()
-            .unwrap()
+            .and_then(|u| u.user.token.as_ref())
+            .context("Rancher kubeconfig contains no user token")?
             .to_string(),
     );
 
@@ -1,6 +1,6 @@
 use colored::Colorize;
-use dialoguer::{Password, Select};
-use eyre::eyre;
+use dialoguer::Password;
+use eyre::{eyre, ContextCompat, WrapErr};
 use keyring::Entry;
 use log::info;
 use rancher::RancherClient;
@@ -12,7 +12,13 @@ use super::{
     kubernetes::{self, Cluster},
     KubeConfig,
 };
-use crate::get_config;
+use crate::{
+    error::{ErrorKind, OgError, ResultExt},
+    get_config, prompt,
+};
+
+/// How to pass the token when og can't prompt for it
+const TOKEN_HINT: &str = "the token as OG_RANCHER_TOKEN to og kube init";
 
 #[derive(Deserialize)]
 
@@ -61,19 +67,22 @@ pub fn get_rancher_token() -> eyre::Result<String> {
     Ok(entry.get_password()?)
 }
 
-pub async fn get_rancher_clusters(rancher_token: &str) -> Vec<Cluster> {
+pub async fn get_rancher_clusters(rancher_token: &str) -> eyre::Result<Vec<Cluster>> {
     let rancher_client = RancherClient::new(
         rancher_token.to_string(),
         String::from(&get_config().rancher_base_url),
     );
-    let clusters_result = rancher_client.clusters().await;
-
-    if let Ok(clusters) = clusters_result {
-        let clusters = clusters
-            .data
-            .into_iter()
-            .map(|c| Cluster {
-                id: c.id,
+    let clusters = rancher_client
+        .clusters()
+        .await
+        .wrap_err("Unable to fetch clusters from Rancher")
+        .error_kind(ErrorKind::Network)?;
+
+    clusters
+        .data
+        .into_iter()
+        .map(|c| {
+            Ok(Cluster {
                 name: c.name[..c.name.len() - kubernetes::RANCHER_CLUSTER_SUFFIX_LENGTH]
                     .to_string(),
                 name_suffix: c.name[c.name.len() - kubernetes::RANCHER_CLUSTER_SUFFIX_LENGTH..]
@@ -81,37 +90,45 @@ pub async fn get_rancher_clusters(rancher_token: &str) -> Vec<Cluster> {
                 server: c
                     .links
                     .get("self")
-                    .unwrap()
+                    .with_context(|| format!("Rancher cluster {} has no self link", c.name))?
                     .replace("v3", "k8s")
                     .to_string(),
-                token_url: Some(c.actions.get("generateKubeconfig").unwrap().to_string()),
+                token_url: Some(
+                    c.actions
+                        .get("generateKubeconfig")
+                        .with_context(|| {
+                            format!(
+                                "Rancher cluster {} has no generateKubeconfig action",
+                                c.name
+                            )
+                        })?
+                        .to_string(),
+                ),
+                id: c.id,
             })
-            .collect();
-
-        return clusters;
-    }
-
-    Vec::new()
+        })
+        .collect()
 }
 
 pub fn add_rancher_token() -> eyre::Result<()> {
-    let selected_option = Select::new()
-        .with_prompt("Would you like to create a new Rancher API token or use an existing one")
-        .default(0)
-        .items(&["New Token", "Existing Token"])
-        .interact()
-        .unwrap();
+    let selected_option = prompt::select(
+        "Would you like to create a new Rancher API token or use an existing one",
+        &["New Token", "Existing Token"],
+        0,
+        TOKEN_HINT,
+    )?;
 
     match selected_option {
         0 => create_new_rancher_token()?,
-        1 => add_existing_rancher_token()?,
-        _ => unimplemented!(),
+        _ => add_existing_rancher_token()?,
     };
 
     Ok(())
 }
 
 fn create_new_rancher_token() -> eyre::Result<()> {
+    // The token has to be entered afterwards, so don't open the browser in vain
+    prompt::ensure_interactive(TOKEN_HINT)?;
     let mut rancher_url = get_config().rancher_base_url.clone();
     rancher_url.push_str("/dashboard/account/create-key");
 
@@ -130,19 +147,19 @@ fn create_new_rancher_token() -> eyre::Result<()> {
 }
 
 fn add_existing_rancher_token() -> eyre::Result<()> {
+    prompt::ensure_interactive(TOKEN_HINT)?;
     let token = Password::new()
         .with_prompt("Please enter your Rancher API token")
-        .validate_with(|t: &String| -> Result<(), &str> {
-            if t.starts_with("token-") {
-                Ok(())
-            } else {
-                Err("Entered token format seems to be invalid. Token must start with 'token-'")
-            }
-        })
-        .interact()
-        .unwrap();
+        .validate_with(|t: &String| validate_rancher_token(t))
+        .interact()?;
+
+    store_rancher_token(&token)
+}
 
-    set_rancher_token(&token)?;
+pub fn store_rancher_token(token: &str) -> eyre::Result<()> {
+    validate_rancher_token(token).map_err(|e| OgError::msg(ErrorKind::Usage, e))?;
+
+    set_rancher_token(token)?;
     info!(
         "{}",
         "Rancher API token persisted in system credential store".green()
@@ -151,6 +168,14 @@ fn add_existing_rancher_token() -> eyre::Result<()> {
     Ok(())
 }
 
+fn validate_rancher_token(token: &str) -> Result<(), &'static str> {
+    if token.starts_with("token-") {
+        Ok(())
+    } else {
+        Err("Entered token format seems to be invalid. Token must start with 'token-'")
+    }
+}
+
 #[cfg(target_family = "unix")]
 pub fn set_rancher_token(token: &str) -> eyre::Result<()> {
     let entry = Entry::new(kubernetes::KEYRING_SERVICE_ID, kubernetes::KEYRING_KEY)?;
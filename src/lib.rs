@@ -1,8 +1,13 @@
 pub mod common_docker;
 pub mod config;
+pub mod connection_string;
+pub mod container_runtime;
 pub mod dg;
 pub mod doctor;
 pub mod dotnet;
+pub mod duration;
+pub mod env;
+pub mod error;
 pub mod fix;
 #[cfg(feature = "git")]
 pub mod git;
@@ -10,7 +15,11 @@ pub mod graphql;
 pub mod kube;
 pub mod mongo_db;
 pub mod network;
+pub mod output;
 pub mod plugin;
+pub mod ports;
+pub mod project;
+pub mod prompt;
 pub mod search;
 pub mod sql;
 
@@ -1,5 +1,5 @@
-use clap::{error::ErrorKind, CommandFactory, Parser, Subcommand};
-use eyre::Result;
+use clap::{error::ErrorKind, Command, CommandFactory, Parser, Subcommand};
+use eyre::WrapErr;
 use log::LevelFilter;
 use simplelog::{ColorChoice, ConfigBuilder, TermLogger, TerminalMode};
 use std::{env, process};
@@ -11,11 +11,15 @@ use og_cli::{
     dg::{DgCliPlugin, DgCommand},
     doctor::{self, DoctorCommand},
     dotnet::{DotnetCommand, DotnetPlugin},
+    env::{EnvCommand, EnvPlugin},
+    error::Result,
     fix::{self, FixCommand},
     graphql::{GraphQlCommand, GraphQlPlugin},
     kube::{KubernetesCommand, KubernetesPlugin},
     mongo_db::{MongoDbCommand, MongoDbPlugin},
     network::{NetworkCommand, NetworkPlugin},
+    output::{self, OutputFormat},
+    prompt,
     search::{SearchCommand, SearchPlugin},
     sql::{SqlCommand, SqlPlugin},
 };
@@ -26,6 +30,15 @@ use og_cli::{
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Accept all confirmations and default choices without prompting
+    #[arg(short = 'y', long, global = true)]
+    yes: bool,
+    /// Never prompt, fail when a choice is not given by a flag (default without a TTY or with CI=true)
+    #[arg(long, global = true)]
+    no_input: bool,
+    /// Format of printed data like query results
+    #[arg(long, global = true, value_enum, default_value_t)]
+    output: OutputFormat,
 }
 
 #[derive(Subcommand)]
@@ -33,11 +46,12 @@ enum Commands {
     Sql(SqlCommand),
     #[clap(name = "mongodb")]
     MongoDb(MongoDbCommand),
+    Env(EnvCommand),
     #[clap(name = "graphql")]
     GraphQl(GraphQlCommand),
     Search(SearchCommand),
     Doctor(DoctorCommand),
-    #[clap(name = "fix-beta")]
+    #[clap(name = "fix-beta", visible_alias = "fix")]
     Fix(FixCommand),
     #[clap(name = "dotnet")]
     Dotnet(DotnetCommand),
@@ -46,14 +60,21 @@ enum Commands {
     #[cfg(feature = "git")]
     #[clap(name = "git-beta")]
     Git(GitCommand),
-    #[clap(name = "dg-beta")]
+    #[clap(name = "dg-beta", visible_alias = "dg")]
     Dg(DgCommand),
     #[clap(name = "network-beta")]
     Network(NetworkCommand),
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
+    if let Err(error) = run().await {
+        eprintln!("Error: {error:?}");
+        process::exit(error.exit_code());
+    }
+}
+
+async fn run() -> Result<()> {
     TermLogger::init(
         LevelFilter::Info,
         ConfigBuilder::new()
@@ -62,76 +83,82 @@ async fn main() -> Result<()> {
             .build(),
         TerminalMode::Mixed,
         ColorChoice::Never,
-    )?;
+    )
+    .wrap_err("Unable to initialize logging")?;
 
     config::init_config().await?;
 
     let cli = Cli::try_parse();
     match cli {
         Ok(c) => {
+            prompt::init(c.yes, c.no_input);
+            output::init(c.output);
             match c.command {
-                Some(Commands::MongoDb(mongodb_command)) => MongoDbPlugin::run(mongodb_command),
+                Some(Commands::MongoDb(mongodb_command)) => {
+                    MongoDbPlugin::run(mongodb_command).await?
+                }
                 Some(Commands::Sql(sql_command)) => SqlPlugin::run(sql_command).await?,
-                Some(Commands::Dotnet(command)) => DotnetPlugin::run(command).expect("Reason"),
+                Some(Commands::Env(env_command)) => EnvPlugin::run(env_command).await?,
+                Some(Commands::Dotnet(command)) => DotnetPlugin::run(command)?,
                 #[cfg(feature = "git")]
-                Some(Commands::Git(git_command)) => GitPlugin::run(git_command),
-                Some(Commands::Fix(_)) => {
-                    fix::FixPlugin::run()?;
-                }
-                Some(Commands::Doctor(dr_command)) => doctor::run(dr_command),
+                Some(Commands::Git(git_command)) => GitPlugin::run(git_command)?,
+                Some(Commands::Fix(fix_command)) => fix::FixPlugin::run(fix_command).await?,
+                Some(Commands::Doctor(dr_command)) => doctor::run(dr_command)?,
                 Some(Commands::Kubernetes(kubernetes_command)) => {
                     KubernetesPlugin::run(kubernetes_command).await?
                 }
                 Some(Commands::GraphQl(graphql_command)) => {
                     GraphQlPlugin::run(graphql_command)?;
                 }
-                Some(Commands::Search(search_command)) => SearchPlugin::run(search_command).await?, // default is to forward unknown commands to the python dg cli
+                Some(Commands::Search(search_command)) => SearchPlugin::run(search_command).await?,
                 Some(Commands::Dg(dg_command)) => {
-                    DgCliPlugin::run(dg_command)?;
+                    let exit_code = DgCliPlugin::run(dg_command)?;
+                    process::exit(exit_code);
                 }
                 Some(Commands::Network(network_command)) => {
-                    NetworkPlugin::run(network_command);
+                    NetworkPlugin::run(network_command)?;
                 }
                 None => {
-                    let mut cmd = Cli::command();
-                    cmd.build();
-                    let _ = cmd.print_help();
+                    let _ = command_with_dg_help().print_help();
                     process::exit(0);
                 }
             }
         }
         Err(e) => {
+            // default is to forward unknown commands to the python dg cli
             let args: Vec<String> = env::args().skip(1).collect();
+            if is_dg_command(&args) {
+                let exit_code = DgCliPlugin::run_from_plain_args(args)?;
+                process::exit(exit_code);
+            }
 
-            let mut cmd = Cli::command();
-            cmd.build();
-
-            match e.kind() {
-                ErrorKind::InvalidValue
-                | ErrorKind::UnknownArgument
-                | ErrorKind::NoEquals
-                | ErrorKind::ValueValidation
-                | ErrorKind::TooManyValues
-                | ErrorKind::TooFewValues
-                | ErrorKind::WrongNumberOfValues
-                | ErrorKind::ArgumentConflict
-                | ErrorKind::MissingRequiredArgument
-                | ErrorKind::MissingSubcommand
-                | ErrorKind::InvalidUtf8
-                | ErrorKind::DisplayHelp
-                | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
-                | ErrorKind::DisplayVersion
-                | ErrorKind::Io
-                | ErrorKind::Format => {
-                    e.print()?;
-                    process::exit(1);
-                }
-                _ => {
-                    DgCliPlugin::run_from_plain_args(args)?;
-                }
+            if e.kind() == ErrorKind::DisplayHelp && args.iter().all(|a| a.starts_with('-')) {
+                let _ = command_with_dg_help().print_help();
+                process::exit(0);
             }
+
+            e.exit();
         }
     }
 
     Ok(())
 }
+
+/// Whether the first argument is not an og command and should be handled by the DG CLI
+fn is_dg_command(args: &[String]) -> bool {
+    let mut cmd = Cli::command();
+    cmd.build();
+
+    args.first()
+        .is_some_and(|a| !a.starts_with('-') && cmd.find_subcommand(a).is_none())
+}
+
+fn command_with_dg_help() -> Command {
+    let cmd = Cli::command();
+    match DgCliPlugin::list_commands() {
+        Some(dg_commands) => {
+            cmd.after_help(format!("DG CLI commands (forwarded to dg):\n{dg_commands}"))
+        }
+        None => cmd,
+    }
+}
@@ -0,0 +1,93 @@
+use clap::{Args, Subcommand};
+use std::time::Duration;
+
+use crate::{connection_string::ConnectionStringArgs, duration::parse_duration};
+
+/// Run a MongoDB server inside a docker container
+#[derive(Args, Debug)]
+pub struct MongoDbCommand {
+    #[command(subcommand)]
+    pub command: MongoDbSubCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MongoDbSubCommands {
+    Start(StartArgs),
+    Stop,
+    /// Restart the container, e.g. after changing its settings inside
+    Restart,
+    /// Remove the container
+    Remove(RemoveArgs),
+    /// Show the container's image, health, port, volume and connection string
+    Status,
+    /// Print the MongoDB logs
+    Logs(LogsArgs),
+    /// Print a connection string for the server
+    ConnectionString(MongoDbConnectionStringArgs),
+    /// Keep the root user in the system credential store instead of the og config
+    Credentials {
+        #[command(subcommand)]
+        command: CredentialsSubcommands,
+    },
+    /// Work with the compose file og runs MongoDB with
+    Compose {
+        #[command(subcommand)]
+        command: ComposeSubcommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ComposeSubcommands {
+    /// Print the compose file, e.g. to commit it to a project
+    Export,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CredentialsSubcommands {
+    /// Prompt for the root user and password and store them
+    Set,
+    /// Delete the stored credentials, the og config applies again
+    Clear,
+}
+
+#[derive(Args, Debug)]
+pub struct StartArgs {
+    /// Tag of the mongo image, e.g. 6.0 [default: latest]
+    #[arg(long)]
+    pub version: Option<String>,
+    /// Host port MongoDB is published on [default: 27017]
+    #[arg(short, long)]
+    pub port: Option<u16>,
+    /// Run a single-node replica set for transactions and change streams, kept until the container is removed
+    #[arg(long)]
+    pub replica_set: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RemoveArgs {
+    /// Also delete the data volume with all databases
+    #[arg(short, long)]
+    pub volumes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    /// Keep printing new log lines
+    #[arg(short, long)]
+    pub follow: bool,
+    /// Only logs of the last duration, e.g. 10m
+    #[arg(long, value_parser = parse_duration)]
+    pub since: Option<Duration>,
+    /// Only the last lines
+    #[arg(long)]
+    pub tail: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct MongoDbConnectionStringArgs {
+    /// Database to connect to
+    #[arg(short, long)]
+    pub database: Option<String>,
+    #[command(flatten)]
+    pub connection_string: ConnectionStringArgs,
+}
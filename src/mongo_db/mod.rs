@@ -0,0 +1,7 @@
+mod commands;
+mod mongo_db;
+mod replica_set;
+mod settings;
+
+pub use commands::MongoDbCommand;
+pub use mongo_db::MongoDbPlugin;
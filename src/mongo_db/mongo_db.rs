@@ -0,0 +1,505 @@
+use bollard::{
+    container::{
+        InspectContainerOptions, ListContainersOptions, RestartContainerOptions,
+        StopContainerOptions,
+    },
+    errors::Error as DockerError,
+    models::{ContainerInspectResponse, ContainerStateStatusEnum},
+    Docker,
+};
+use dialoguer::{Input, Password};
+use eyre::Result;
+use log::{info, warn};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Duration,
+};
+
+use super::{
+    commands::{ComposeSubcommands, CredentialsSubcommands, MongoDbCommand, MongoDbSubCommands},
+    replica_set::{self, initiate, replica_set, REPLICA_SET, REPLICA_SET_LABEL},
+    settings::{self, Credentials, MongoDbSettings, CONTAINER_PORT},
+};
+use crate::{
+    common_docker::{
+        container_fields, container_name, host_port, logs, project_name, ComposeFile,
+        DockerCompose, Healthcheck, NamedVolume, PortMapping, Service, VolumeMount, PROJECT_LABEL,
+        SERVICE_LABEL,
+    },
+    config::{get_config, MongoDbConfig},
+    connection_string::{self, ConnectionFormat, Endpoint},
+    container_runtime::{self, runtime},
+    doctor::{DoctorFailure, DoctorSuccess},
+    error::{self, ErrorKind, OgError},
+    output,
+    plugin::Plugin,
+    prompt,
+};
+
+const SERVICE: &str = "mongodb";
+const SERVICE_NAME: &str = "mongodb-local";
+/// Older versions ran every stack as the compose project `test`
+const LEGACY_PROJECT: &str = "test";
+/// Mount point of the data volume inside the container
+const DATA_DIR: &str = "/data/db";
+const USERNAME_VARIABLE: &str = "MONGO_INITDB_ROOT_USERNAME";
+const PASSWORD_VARIABLE: &str = "MONGO_INITDB_ROOT_PASSWORD";
+/// How long `start --replica-set` waits for the node before initiating the set
+const REPLICA_SET_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub struct MongoDbPlugin;
+
+impl MongoDbPlugin {
+    pub async fn run(cli: MongoDbCommand) -> error::Result<()> {
+        let docker = container_runtime::connect()?;
+        let container = container_name(&project_name(SERVICE), SERVICE_NAME);
+        let config = &get_config().mongodb;
+
+        match cli.command {
+            MongoDbSubCommands::Start(args) => {
+                warn_on_legacy_project(&docker).await;
+                let settings = MongoDbSettings::resolve(config, args.version, args.port);
+                let existing = inspect(&docker, &container).await?;
+                let current = replica_set(existing.as_ref());
+                let replica_set = args.replica_set || current.is_some();
+                let mut compose = compose(&settings, replica_set)?;
+                let port = compose
+                    .published_port(SERVICE_NAME, target_port(&settings, replica_set))
+                    .unwrap_or(settings.port);
+
+                if is_running(existing.as_ref())
+                    && current.is_some() == replica_set
+                    && !is_changed(existing.as_ref(), &settings.image, port)
+                {
+                    info!("Container {container} is already running, nothing to do.");
+                } else if replica_set {
+                    info!(
+                        "Starting MongoDB {} as replica set {REPLICA_SET}",
+                        settings.image
+                    );
+                    compose.start_and_wait(REPLICA_SET_TIMEOUT).await?;
+                } else {
+                    info!("Starting MongoDB {}", settings.image);
+                    compose.start().await?;
+                }
+
+                if replica_set {
+                    let port =
+                        published_port(&container, inspect(&docker, &container).await?.as_ref())?;
+                    initiate(
+                        &docker,
+                        &container,
+                        port,
+                        &settings.credentials.username,
+                        &settings.credentials.password,
+                    )
+                    .await?;
+                    println!(
+                        "{}",
+                        uri(
+                            ConnectionFormat::Uri,
+                            port,
+                            None,
+                            Some(REPLICA_SET),
+                            &settings.credentials,
+                        )?
+                    );
+                }
+            }
+            MongoDbSubCommands::Stop => match inspect(&docker, &container).await? {
+                None => info!("Container {container} doesn't exist, nothing to do."),
+                Some(inspect) if !is_running(Some(&inspect)) => {
+                    info!("Container {container} is already stopped, nothing to do.")
+                }
+                Some(_) => {
+                    info!("Stopping container {container}...");
+                    docker
+                        .stop_container(&container, None::<StopContainerOptions>)
+                        .await
+                        .map_err(eyre::Report::from)?;
+                    info!("Container {container} stopped");
+                }
+            },
+            MongoDbSubCommands::Restart => {
+                ensure_exists(&docker, &container).await?;
+                info!("Restarting container {container}...");
+                docker
+                    .restart_container(&container, Some(RestartContainerOptions { t: 10 }))
+                    .await
+                    .map_err(eyre::Report::from)?;
+                info!("Container {container} restarted");
+            }
+            MongoDbSubCommands::Remove(args) => {
+                let existing = inspect(&docker, &container).await?;
+                if existing.is_none() && !args.volumes {
+                    info!("Container {container} doesn't exist, nothing to remove.");
+                    return Ok(());
+                }
+                let settings = MongoDbSettings::resolve(config, None, None);
+                let compose = compose(&settings, replica_set(existing.as_ref()).is_some())?;
+                if args.volumes {
+                    let volume = &settings.volume;
+                    if !prompt::confirm(
+                        &format!("Delete volume {volume}? All databases in it will be lost"),
+                        false,
+                    )? {
+                        Err(OgError::msg(
+                            ErrorKind::UserAbort,
+                            format!("Volume {volume} was not deleted"),
+                        ))?;
+                    }
+                    compose.stop_and_remove_volumes().await?;
+                } else {
+                    compose.stop().await?;
+                }
+            }
+            MongoDbSubCommands::Status => {
+                let settings = MongoDbSettings::resolve(config, None, None);
+                status(&docker, &container, &settings.credentials).await?
+            }
+            MongoDbSubCommands::Logs(args) => {
+                ensure_exists(&docker, &container).await?;
+                logs(&docker, &container, args.follow, args.since, args.tail).await?;
+            }
+            MongoDbSubCommands::Compose {
+                command: ComposeSubcommands::Export,
+            } => {
+                let existing = inspect(&docker, &container).await.ok().flatten();
+                let settings = MongoDbSettings::resolve(config, None, None);
+                print!(
+                    "{}",
+                    compose_file(&settings, replica_set(existing.as_ref()).is_some()).to_yaml()?
+                )
+            }
+            MongoDbSubCommands::ConnectionString(args) => {
+                let existing = inspect(&docker, &container).await?;
+                let settings = MongoDbSettings::resolve(config, None, None);
+                let format = args
+                    .connection_string
+                    .format
+                    .unwrap_or(ConnectionFormat::Uri);
+                connection_string::print(
+                    &uri(
+                        format,
+                        published_port(&container, existing.as_ref())?,
+                        args.database.as_deref(),
+                        replica_set(existing.as_ref()).as_deref(),
+                        &settings.credentials,
+                    )?,
+                    args.connection_string.copy,
+                )?;
+            }
+            MongoDbSubCommands::Credentials {
+                command: CredentialsSubcommands::Set,
+            } => set_credentials(config)?,
+            MongoDbSubCommands::Credentials {
+                command: CredentialsSubcommands::Clear,
+            } => {
+                settings::clear_credentials()?;
+                info!("MongoDB credentials removed from system credential store");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn compose(settings: &MongoDbSettings, replica_set: bool) -> Result<DockerCompose> {
+    let file = compose_file(settings, replica_set);
+    let compose = if replica_set {
+        // The member is announced as localhost:<port>, the host port can't differ
+        DockerCompose::with_fixed_ports(
+            &project_name(SERVICE),
+            &file,
+            "pick another one with: og mongodb start --port <PORT>",
+        )?
+    } else {
+        DockerCompose::new(&project_name(SERVICE), &file)?
+    };
+
+    Ok(compose.with_variables(BTreeMap::from([
+        (
+            USERNAME_VARIABLE.into(),
+            settings.credentials.username.clone(),
+        ),
+        (
+            PASSWORD_VARIABLE.into(),
+            settings.credentials.password.clone(),
+        ),
+    ])))
+}
+
+/// A replica set member listens on the port it is announced with
+fn target_port(settings: &MongoDbSettings, replica_set: bool) -> u16 {
+    if replica_set {
+        settings.port
+    } else {
+        CONTAINER_PORT
+    }
+}
+
+fn compose_file(settings: &MongoDbSettings, replica_set: bool) -> ComposeFile {
+    let target = target_port(settings, replica_set);
+    let mut service = Service {
+        image: settings.image.clone(),
+        // Placeholders, so the credentials aren't written to disk or exported
+        environment: BTreeMap::from([
+            (
+                USERNAME_VARIABLE.into(),
+                format!("${{{USERNAME_VARIABLE}}}"),
+            ),
+            (
+                PASSWORD_VARIABLE.into(),
+                format!("${{{PASSWORD_VARIABLE}}}"),
+            ),
+        ]),
+        ports: vec![PortMapping {
+            target,
+            published: settings.port,
+            host_ip: None,
+            protocol: None,
+        }],
+        volumes: vec![VolumeMount::volume("mongodb-data", DATA_DIR)],
+        healthcheck: Some(Healthcheck {
+            test: vec![
+                "CMD".into(),
+                "mongosh".into(),
+                "--quiet".into(),
+                "--port".into(),
+                target.to_string(),
+                "--eval".into(),
+                "db.adminCommand('ping')".into(),
+            ],
+            interval: Some("10s".into()),
+            timeout: Some("5s".into()),
+            retries: Some(10),
+            start_period: Some("30s".into()),
+        }),
+        ..Default::default()
+    };
+    if replica_set {
+        service.command = Some(replica_set::command(target));
+        service
+            .labels
+            .insert(REPLICA_SET_LABEL.into(), REPLICA_SET.into());
+    }
+
+    ComposeFile {
+        services: BTreeMap::from([(SERVICE_NAME.into(), service)]),
+        volumes: BTreeMap::from([(
+            "mongodb-data".into(),
+            NamedVolume {
+                name: Some(settings.volume.clone()),
+                ..Default::default()
+            },
+        )]),
+        ..Default::default()
+    }
+}
+
+fn uri(
+    format: ConnectionFormat,
+    port: u16,
+    database: Option<&str>,
+    replica_set: Option<&str>,
+    credentials: &Credentials,
+) -> Result<String> {
+    Ok(connection_string::mongodb(
+        format,
+        &Endpoint {
+            host: "localhost",
+            port,
+            user: &credentials.username,
+            password: &credentials.password,
+            database,
+        },
+        replica_set,
+    )?)
+}
+
+async fn inspect(docker: &Docker, container: &str) -> Result<Option<ContainerInspectResponse>> {
+    match docker
+        .inspect_container(container, None::<InspectContainerOptions>)
+        .await
+    {
+        Ok(inspect) => Ok(Some(inspect)),
+        Err(DockerError::DockerResponseServerError {
+            status_code: 404, ..
+        }) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+async fn ensure_exists(docker: &Docker, container: &str) -> Result<ContainerInspectResponse> {
+    match inspect(docker, container).await? {
+        Some(inspect) => Ok(inspect),
+        None => Err(OgError::msg(
+            ErrorKind::Usage,
+            format!("Container {container} doesn't exist, create it with: og mongodb start"),
+        ))?,
+    }
+}
+
+fn is_running(inspect: Option<&ContainerInspectResponse>) -> bool {
+    inspect
+        .and_then(|i| i.state.as_ref())
+        .and_then(|s| s.status)
+        == Some(ContainerStateStatusEnum::RUNNING)
+}
+
+async fn status(docker: &Docker, container: &str, credentials: &Credentials) -> Result<()> {
+    let Some(inspect) = inspect(docker, container).await? else {
+        info!("Container {container} doesn't exist, create it with: og mongodb start");
+        return Ok(());
+    };
+
+    let container_port = container_port(&inspect);
+    let port = host_port(&inspect, container_port);
+    let replica_set = replica_set(Some(&inspect));
+    let mut record = vec![("container", Value::from(container))];
+    record.extend(container_fields(docker, &inspect, container_port, DATA_DIR).await?);
+    record.push(("replica_set", Value::from(replica_set.clone())));
+    record.push((
+        "connection_string",
+        Value::from(port.and_then(|port| {
+            uri(
+                ConnectionFormat::Uri,
+                port,
+                None,
+                replica_set.as_deref(),
+                credentials,
+            )
+            .ok()
+        })),
+    ));
+    output::print_record(&record);
+
+    Ok(())
+}
+
+/// A stack started by an older version holds the port, it has to be stopped under its old project name
+async fn warn_on_legacy_project(docker: &Docker) {
+    let project = format!("{PROJECT_LABEL}={LEGACY_PROJECT}");
+    let service = format!("{SERVICE_LABEL}={SERVICE_NAME}");
+    let options = ListContainersOptions {
+        filters: HashMap::from([("label", vec![project.as_str(), service.as_str()])]),
+        ..Default::default()
+    };
+
+    if docker
+        .list_containers(Some(options))
+        .await
+        .is_ok_and(|c| !c.is_empty())
+    {
+        warn!(
+            "MongoDB is still running as compose project '{LEGACY_PROJECT}', stop it first with: docker stop {LEGACY_PROJECT}-{SERVICE_NAME}-1"
+        );
+    }
+}
+
+/// Port MongoDB listens on inside the container, replica set members listen on their host port
+fn container_port(inspect: &ContainerInspectResponse) -> u16 {
+    inspect
+        .host_config
+        .as_ref()
+        .and_then(|c| c.port_bindings.as_ref())
+        .and_then(|b| b.keys().find_map(|k| k.strip_suffix("/tcp")?.parse().ok()))
+        .unwrap_or(CONTAINER_PORT)
+}
+
+/// Host port of the running container
+fn published_port(container: &str, inspect: Option<&ContainerInspectResponse>) -> Result<u16> {
+    match inspect.filter(|i| is_running(Some(i))) {
+        Some(inspect) => {
+            let container_port = container_port(inspect);
+            host_port(inspect, container_port).ok_or_else(|| {
+                OgError::msg(
+                    ErrorKind::Docker,
+                    format!("Container {container} doesn't publish port {container_port}"),
+                )
+                .into()
+            })
+        }
+        None => Err(OgError::msg(
+            ErrorKind::Usage,
+            "MongoDB is not running, start it with: og mongodb start",
+        ))?,
+    }
+}
+
+/// The running container has another image or host port than the compose file, starting recreates it
+fn is_changed(inspect: Option<&ContainerInspectResponse>, image: &str, port: u16) -> bool {
+    let Some(inspect) = inspect else {
+        return false;
+    };
+    let current = inspect.config.as_ref().and_then(|c| c.image.as_deref());
+
+    let published = host_port(inspect, container_port(inspect));
+
+    current.is_some_and(|i| i != image) || published.is_some_and(|p| p != port)
+}
+
+fn set_credentials(config: &MongoDbConfig) -> Result<()> {
+    prompt::ensure_interactive("the user as mongodb.username in the og config")?;
+    let current = MongoDbSettings::resolve(config, None, None).credentials;
+
+    let username = Input::<String>::new()
+        .with_prompt("MongoDB root user")
+        .default(current.username)
+        .interact_text()?;
+    let password = Password::new()
+        .with_prompt("Password")
+        .with_confirmation("Repeat password", "Passwords don't match")
+        .interact()?;
+    settings::store_credentials(&Credentials { username, password })?;
+
+    info!("MongoDB credentials persisted in system credential store");
+    warn!("MongoDB only creates the root user on a new data volume, recreate it with: og mongodb remove --volumes");
+    Ok(())
+}
+
+impl Plugin for MongoDbPlugin {
+    fn doctor(&self) -> Vec<Result<DoctorSuccess, DoctorFailure>> {
+        vec![runtime().doctor("MongoDB")]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replica_set_keeps_port_inside_and_out() {
+        let settings = MongoDbSettings {
+            image: "mongo:6.0".into(),
+            port: 27018,
+            credentials: Credentials {
+                username: "admin".into(),
+                password: "admin".into(),
+            },
+            volume: "mongodb-data".into(),
+        };
+        let file = compose_file(&settings, true);
+
+        let service = &file.services[SERVICE_NAME];
+        assert_eq!(service.image, "mongo:6.0");
+        assert_eq!(service.labels[REPLICA_SET_LABEL], REPLICA_SET);
+        assert!(service
+            .command
+            .as_deref()
+            .is_some_and(|c| c.contains("--port 27018")));
+        assert_eq!(service.ports[0].target, 27018);
+        assert_eq!(service.ports[0].published, 27018);
+
+        assert_eq!(
+            service.environment[PASSWORD_VARIABLE],
+            "${MONGO_INITDB_ROOT_PASSWORD}"
+        );
+
+        let standalone = &compose_file(&settings, false).services[SERVICE_NAME];
+        assert_eq!(standalone.command, None);
+        assert_eq!(standalone.ports[0].target, 27017);
+        assert_eq!(standalone.ports[0].published, 27018);
+    }
+}
@@ -0,0 +1,124 @@
+use bollard::{
+    exec::{CreateExecOptions, StartExecResults},
+    models::ContainerInspectResponse,
+    Docker,
+};
+use eyre::Result;
+use futures_util::TryStreamExt;
+use log::{debug, info};
+
+use crate::error::{ErrorKind, OgError};
+
+/// Name of the single-node replica set
+pub const REPLICA_SET: &str = "rs0";
+/// Marks containers started with `--replica-set`, so later commands keep the mode
+pub const REPLICA_SET_LABEL: &str = "og-cli.replica-set";
+/// Replica sets with authentication need a key file, any key does for a single node
+const KEY_FILE: &str = "/tmp/og-keyfile";
+
+/// Runs mongod as a replica set, through the image's entrypoint so the root user is still created
+pub fn command(port: u16) -> String {
+    format!(
+        "bash -c 'head -c 756 /dev/urandom | base64 > {KEY_FILE} && chmod 400 {KEY_FILE} && \
+         chown mongodb:mongodb {KEY_FILE} && exec docker-entrypoint.sh mongod --replSet {REPLICA_SET} \
+         --bind_ip_all --port {port} --keyFile {KEY_FILE}'"
+    )
+}
+
+/// The replica set a container was started with
+pub fn replica_set(inspect: Option<&ContainerInspectResponse>) -> Option<String> {
+    inspect?
+        .config
+        .as_ref()?
+        .labels
+        .as_ref()?
+        .get(REPLICA_SET_LABEL)
+        .cloned()
+}
+
+/// Initiates the replica set unless it already is, its member is reachable from the host on `port`
+pub async fn initiate(
+    docker: &Docker,
+    container: &str,
+    port: u16,
+    user: &str,
+    password: &str,
+) -> Result<()> {
+    let script = initiate_script(port);
+    let port = port.to_string();
+    let cmd = vec![
+        "mongosh",
+        "--quiet",
+        "--port",
+        &port,
+        "-u",
+        user,
+        "-p",
+        password,
+        "--authenticationDatabase",
+        "admin",
+        "--eval",
+        &script,
+    ];
+
+    let created = docker
+        .create_exec(
+            container,
+            CreateExecOptions {
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                cmd: Some(cmd),
+                ..Default::default()
+            },
+        )
+        .await?;
+    let mut output = String::new();
+    if let StartExecResults::Attached {
+        output: mut stream, ..
+    } = docker.start_exec(&created.id, None).await?
+    {
+        while let Some(chunk) = stream.try_next().await? {
+            output.push_str(&String::from_utf8_lossy(&chunk.into_bytes()));
+        }
+    }
+    let exit_code = docker
+        .inspect_exec(&created.id)
+        .await?
+        .exit_code
+        .unwrap_or_default();
+
+    debug!("rs.initiate: {}", output.trim());
+    match output.trim() {
+        "initiated" => info!("Replica set {REPLICA_SET} initiated"),
+        "exists" => debug!("Replica set {REPLICA_SET} is already initiated"),
+        _ => Err(OgError::msg(
+            ErrorKind::Docker,
+            format!(
+                "Could not initiate replica set {REPLICA_SET} (exit code {exit_code}): {}",
+                output.trim()
+            ),
+        ))?,
+    }
+
+    Ok(())
+}
+
+/// Prints `exists` or `initiated`, `rs.status()` throws until the set is initiated
+fn initiate_script(port: u16) -> String {
+    format!(
+        "try {{ rs.status(); print('exists') }} catch (e) {{ \
+         rs.initiate({{ _id: '{REPLICA_SET}', members: [{{ _id: 0, host: 'localhost:{port}' }}] }}); \
+         print('initiated') }}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initiates_member_on_host_port() {
+        assert!(initiate_script(27018).contains("host: 'localhost:27018'"));
+        assert!(command(27018).contains("--replSet rs0 --bind_ip_all --port 27018"));
+    }
+}
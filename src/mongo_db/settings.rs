@@ -0,0 +1,161 @@
+use eyre::Result;
+use keyring::Entry;
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::config::MongoDbConfig;
+
+/// Port MongoDB listens on inside the container
+pub const CONTAINER_PORT: u16 = 27017;
+const DEFAULT_VERSION: &str = "latest";
+const DEFAULT_USERNAME: &str = "admin";
+const DEFAULT_PASSWORD: &str = "admin";
+/// Kept from the `test` project, so the data survives the rename of the project
+const DEFAULT_VOLUME: &str = "test_mongodb-data";
+const KEYRING_SERVICE_ID: &str = "og_cli_mongodb";
+const KEYRING_KEY: &str = "credentials";
+
+/// The MongoDB server `og mongodb` runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct MongoDbSettings {
+    pub image: String,
+    /// Host port
+    pub port: u16,
+    pub credentials: Credentials,
+    pub volume: String,
+}
+
+/// Root user, MongoDB only creates it on an empty data volume
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+impl MongoDbSettings {
+    /// Resolves the settings from the command line, the keyring, the config and built-in defaults
+    pub fn resolve(config: &MongoDbConfig, version: Option<String>, port: Option<u16>) -> Self {
+        Self::resolve_with(config, version, port, stored_credentials())
+    }
+
+    fn resolve_with(
+        config: &MongoDbConfig,
+        version: Option<String>,
+        port: Option<u16>,
+        stored: Option<Credentials>,
+    ) -> Self {
+        let version = version
+            .or_else(|| config.version.clone())
+            .unwrap_or_else(|| DEFAULT_VERSION.to_string());
+
+        Self {
+            image: format!("mongo:{version}"),
+            port: port.or(config.port).unwrap_or(CONTAINER_PORT),
+            credentials: stored.unwrap_or_else(|| Credentials {
+                username: config
+                    .username
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_USERNAME.to_string()),
+                password: DEFAULT_PASSWORD.to_string(),
+            }),
+            volume: config
+                .volume
+                .clone()
+                .unwrap_or_else(|| DEFAULT_VOLUME.to_string()),
+        }
+    }
+}
+
+/// Credentials from `og mongodb credentials set`, a missing or unavailable keyring falls back to the config
+pub fn stored_credentials() -> Option<Credentials> {
+    let stored = entry()
+        .and_then(|e| e.get_password())
+        .map_err(|e| match e {
+            keyring::Error::NoEntry => {}
+            e => debug!("Could not read MongoDB credentials from the keyring: {e}"),
+        })
+        .ok()?;
+
+    serde_json::from_str(&stored)
+        .map_err(|e| debug!("Ignoring invalid MongoDB credentials in the keyring: {e}"))
+        .ok()
+}
+
+pub fn store_credentials(credentials: &Credentials) -> Result<()> {
+    Ok(entry()?.set_password(&serde_json::to_string(credentials)?)?)
+}
+
+/// Removes the stored credentials, succeeds if there are none
+pub fn clear_credentials() -> Result<()> {
+    match entry()?.delete_password() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
+
+#[cfg(target_family = "unix")]
+fn entry() -> keyring::Result<Entry> {
+    Entry::new(KEYRING_SERVICE_ID, KEYRING_KEY)
+}
+
+#[cfg(target_family = "windows")]
+fn entry() -> keyring::Result<Entry> {
+    Entry::new_with_target(KEYRING_SERVICE_ID, KEYRING_SERVICE_ID, KEYRING_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_match_older_versions() {
+        let settings = MongoDbSettings::resolve_with(&MongoDbConfig::default(), None, None, None);
+
+        assert_eq!(settings.image, "mongo:latest");
+        assert_eq!(settings.port, 27017);
+        assert_eq!(settings.credentials.username, "admin");
+        assert_eq!(settings.volume, "test_mongodb-data");
+    }
+
+    #[test]
+    fn flags_override_config() {
+        let config = MongoDbConfig {
+            version: Some("7.0".into()),
+            port: Some(27018),
+            ..Default::default()
+        };
+
+        let settings =
+            MongoDbSettings::resolve_with(&config, Some("6.0".into()), Some(27019), None);
+
+        assert_eq!(settings.image, "mongo:6.0");
+        assert_eq!(settings.port, 27019);
+        assert_eq!(
+            MongoDbSettings::resolve_with(&config, None, None, None).port,
+            27018
+        );
+    }
+
+    #[test]
+    fn keyring_overrides_config_credentials() {
+        let config = MongoDbConfig {
+            username: Some("dev".into()),
+            ..Default::default()
+        };
+        let stored = Credentials {
+            username: "shop".into(),
+            password: "s3cret".into(),
+        };
+
+        assert_eq!(
+            MongoDbSettings::resolve_with(&config, None, None, Some(stored.clone())).credentials,
+            stored
+        );
+        assert_eq!(
+            MongoDbSettings::resolve_with(&config, None, None, None)
+                .credentials
+                .username,
+            "dev"
+        );
+    }
+}
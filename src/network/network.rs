@@ -3,17 +3,20 @@ use std::process::Command;
 use log::{error, info};
 
 use super::{doctor::Tools, NetworkCommand};
-use crate::doctor::{is_command_in_path, DoctorFailure, DoctorSuccess};
+use crate::{
+    doctor::{is_command_in_path, DoctorFailure, DoctorSuccess},
+    error::{self, ErrorKind, OgError},
+};
 
 pub struct NetworkPlugin;
 
 impl NetworkPlugin {
-    pub fn run(_: NetworkCommand) {
+    pub fn run(_: NetworkCommand) -> error::Result<()> {
         info!("Running Network Tests");
         let ping_result =
             is_command_in_path(Tools::Nslookup.to_string().as_str()).and(Self::ping("10.1.4.4"));
 
-        match ping_result {
+        match &ping_result {
             Ok(x) => {
                 info!("✅  {}", x.message)
             }
@@ -31,7 +34,7 @@ impl NetworkPlugin {
             ))
             .and(Self::nslookup("sqld-az-vm01.intranet.digitec", "10.1.4.4"));
 
-        match nslookup_result {
+        match &nslookup_result {
             Ok(x) => {
                 info!("✅  {}", x.message)
             }
@@ -39,6 +42,12 @@ impl NetworkPlugin {
                 error!("❌ {}", x.message)
             }
         }
+
+        if ping_result.is_err() || nslookup_result.is_err() {
+            return Err(OgError::msg(ErrorKind::Network, "Network tests failed"));
+        }
+
+        Ok(())
     }
 
     pub fn ping(address: &str) -> Result<DoctorSuccess, DoctorFailure> {
@@ -0,0 +1,275 @@
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::sync::OnceLock;
+
+/// Format of the data og prints, e.g. query results
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Csv,
+}
+
+/// Rows with named columns, e.g. a result set of a query
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Configures the output from the global `--output` flag
+pub fn init(format: OutputFormat) {
+    let _ = FORMAT.set(format);
+}
+
+pub fn format() -> OutputFormat {
+    *FORMAT.get_or_init(OutputFormat::default)
+}
+
+/// Prints result sets to stdout in the configured format, JSON is always an array with one entry per set
+pub fn print_tables(tables: &[Table]) {
+    println!("{}", render_tables(tables, format()));
+}
+
+/// Prints a single table to stdout in the configured format, JSON is an array of its rows
+pub fn print_table(table: &Table) {
+    println!("{}", render_single_table(table, format()));
+}
+
+fn render_single_table(table: &Table, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_string_pretty(&table_to_json(table)).unwrap_or_default()
+        }
+        format => render_tables(std::slice::from_ref(table), format),
+    }
+}
+
+/// Prints named values, e.g. the status of a container, to stdout in the configured format
+pub fn print_record(fields: &[(&str, Value)]) {
+    println!("{}", render_record(fields, format()));
+}
+
+fn render_record(fields: &[(&str, Value)], format: OutputFormat) -> String {
+    let text = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        v => v.to_string(),
+    };
+
+    match format {
+        OutputFormat::Table => {
+            let width = fields
+                .iter()
+                .map(|(k, _)| k.len())
+                .max()
+                .unwrap_or_default()
+                + 1;
+            fields
+                .iter()
+                .map(|(k, v)| {
+                    format!("{:<width$} {}", format!("{k}:"), text(v))
+                        .trim_end()
+                        .to_string()
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        OutputFormat::Csv => render_csv(&Table {
+            columns: fields.iter().map(|(k, _)| k.to_string()).collect(),
+            rows: vec![fields.iter().map(|(_, v)| text(v)).collect()],
+        }),
+        OutputFormat::Json => serde_json::to_string_pretty(&Value::Object(
+            fields
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect(),
+        ))
+        .unwrap_or_default(),
+    }
+}
+
+fn render_tables(tables: &[Table], format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Table => tables
+            .iter()
+            .map(render_table)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        OutputFormat::Csv => tables
+            .iter()
+            .map(render_csv)
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+        OutputFormat::Json => {
+            serde_json::to_string_pretty(&Value::Array(tables.iter().map(table_to_json).collect()))
+                .unwrap_or_default()
+        }
+    }
+}
+
+fn render_table(table: &Table) -> String {
+    let widths: Vec<usize> = table
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            table
+                .rows
+                .iter()
+                .filter_map(|r| r.get(i))
+                .map(|v| v.chars().count())
+                .chain(std::iter::once(c.chars().count()))
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+
+    let line = |values: &[String]| {
+        values
+            .iter()
+            .zip(&widths)
+            .map(|(v, w)| format!("{v:<w$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let separator: Vec<String> = widths.iter().map(|w| "-".repeat(*w)).collect();
+
+    std::iter::once(line(&table.columns))
+        .chain(std::iter::once(line(&separator)))
+        .chain(table.rows.iter().map(|r| line(r)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_csv(table: &Table) -> String {
+    std::iter::once(&table.columns)
+        .chain(&table.rows)
+        .map(|r| r.iter().map(|v| csv_field(v)).collect::<Vec<_>>().join(","))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn table_to_json(table: &Table) -> Value {
+    let keys = json_keys(&table.columns);
+    Value::Array(
+        table
+            .rows
+            .iter()
+            .map(|r| {
+                Value::Object(
+                    keys.iter()
+                        .cloned()
+                        .zip(r.iter().map(|v| Value::String(v.clone())))
+                        .collect::<Map<_, _>>(),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Empty and duplicate column names, e.g. of `SELECT 1, 2`, become positional ones like `column2`
+fn json_keys(columns: &[String]) -> Vec<String> {
+    columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            if c.is_empty() || columns.iter().filter(|other| *other == c).count() > 1 {
+                format!("column{}", i + 1)
+            } else {
+                c.clone()
+            }
+        })
+        .collect()
+}
+
+static FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> Table {
+        Table {
+            columns: vec!["name".into(), "comment".into()],
+            rows: vec![
+                vec!["master".into(), "system, \"main\"".into()],
+                vec!["erp".into(), "".into()],
+            ],
+        }
+    }
+
+    #[test]
+    fn renders_aligned_table() {
+        assert_eq!(
+            render_tables(&[table()], OutputFormat::Table),
+            "name    comment\n------  --------------\nmaster  system, \"main\"\nerp"
+        );
+    }
+
+    #[test]
+    fn renders_quoted_csv() {
+        assert_eq!(
+            render_tables(&[table()], OutputFormat::Csv),
+            "name,comment\nmaster,\"system, \"\"main\"\"\"\nerp,"
+        );
+    }
+
+    #[test]
+    fn renders_records() {
+        let fields = [("name", Value::from("erp")), ("port", Value::from(14330))];
+
+        assert_eq!(
+            render_record(&fields, OutputFormat::Table),
+            "name: erp\nport: 14330"
+        );
+        assert_eq!(
+            render_record(&fields, OutputFormat::Csv),
+            "name,port\nerp,14330"
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&render_record(&fields, OutputFormat::Json)).unwrap()
+                ["port"],
+            14330
+        );
+    }
+
+    #[test]
+    fn renders_json_objects() {
+        let json: Value =
+            serde_json::from_str(&render_tables(&[table()], OutputFormat::Json)).unwrap();
+
+        assert_eq!(json[0][0]["name"], "master");
+        assert_eq!(json[0][1]["comment"], "");
+        let json: Value =
+            serde_json::from_str(&render_single_table(&table(), OutputFormat::Json)).unwrap();
+        assert_eq!(json[0]["name"], "master");
+    }
+
+    #[test]
+    fn names_unnamed_and_duplicate_json_columns() {
+        let table = Table {
+            columns: vec!["id".into(), "".into(), "name".into(), "name".into()],
+            rows: vec![vec!["1".into(), "2".into(), "a".into(), "b".into()]],
+        };
+
+        let json: Value =
+            serde_json::from_str(&render_tables(&[table], OutputFormat::Json)).unwrap();
+
+        assert_eq!(
+            json,
+            serde_json::json!([[{"id": "1", "column2": "2", "column3": "a", "column4": "b"}]])
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use bollard::{container::ListContainersOptions, Docker};
+use eyre::{Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    fs,
+    net::{Ipv4Addr, TcpListener},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::{
+    error::{ErrorKind, OgError},
+    prompt,
+};
+
+/// How many ports after a taken one are tried
+const SEARCH_RANGE: u16 = 100;
+
+/// What listens on a host port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Owner {
+    Container(String),
+    Process { name: String, pid: u32 },
+    Unknown,
+}
+
+impl Display for Owner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Owner::Container(name) => write!(f, "container {name}"),
+            Owner::Process { name, pid } => write!(f, "{name} (pid {pid})"),
+            Owner::Unknown => f.write_str("another process"),
+        }
+    }
+}
+
+/// Port `container` can be published on, offering the next free one if `port` is taken
+pub async fn choose(docker: &Docker, port: u16, container: &str) -> Result<u16> {
+    let Some(owner) = conflict(docker, port, container).await else {
+        return Ok(port);
+    };
+    let Some(free) = next_free(port) else {
+        Err(OgError::msg(
+            ErrorKind::Usage,
+            format!(
+                "Port {port} is used by {owner} and no port up to {} is free",
+                port.saturating_add(SEARCH_RANGE)
+            ),
+        ))?
+    };
+
+    warn!("Port {port} is already used by {owner}");
+    if !prompt::confirm(
+        &format!("Publish {container} on port {free} instead?"),
+        true,
+    )? {
+        Err(OgError::msg(
+            ErrorKind::UserAbort,
+            format!("Port {port} is used by {owner}, free it or pick another port"),
+        ))?;
+    }
+    info!("Publishing {container} on port {free}");
+
+    Ok(free)
+}
+
+/// Fails if `port` is taken, for containers whose port can't change anymore
+pub async fn check(docker: &Docker, port: u16, container: &str, hint: &str) -> Result<()> {
+    if let Some(owner) = conflict(docker, port, container).await {
+        Err(OgError::msg(
+            ErrorKind::Usage,
+            format!("Port {port} is already used by {owner}, free it or {hint}"),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Who holds `port`, unless it's free or held by `container` itself
+async fn conflict(docker: &Docker, port: u16, container: &str) -> Option<Owner> {
+    if is_free(port) {
+        return None;
+    }
+
+    match owner(docker, port).await {
+        Owner::Container(name) if name == container => None,
+        owner => Some(owner),
+    }
+}
+
+/// Binds both the wildcard and the loopback address, some platforms allow the former while the latter is taken
+fn is_free(port: u16) -> bool {
+    [Ipv4Addr::UNSPECIFIED, Ipv4Addr::LOCALHOST]
+        .iter()
+        .all(|ip| TcpListener::bind((*ip, port)).is_ok())
+}
+
+fn next_free(port: u16) -> Option<u16> {
+    (port.saturating_add(1)..=port.saturating_add(SEARCH_RANGE)).find(|p| is_free(*p))
+}
+
+async fn owner(docker: &Docker, port: u16) -> Owner {
+    let containers = docker
+        .list_containers(None::<ListContainersOptions<String>>)
+        .await
+        .map_err(|e| debug!("Could not list containers: {e}"))
+        .unwrap_or_default();
+    let container = containers.iter().find(|c| {
+        c.ports
+            .iter()
+            .flatten()
+            .any(|p| p.public_port == Some(port))
+    });
+    if let Some(name) = container.and_then(|c| c.names.as_ref()?.first()) {
+        return Owner::Container(name.trim_start_matches('/').to_string());
+    }
+
+    Command::new("lsof")
+        .args(["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-Fpc"])
+        .output()
+        .ok()
+        .and_then(|o| parse_lsof(&String::from_utf8_lossy(&o.stdout)))
+        .unwrap_or(Owner::Unknown)
+}
+
+/// Reads the first process of `lsof -F pc`, lines like `p1234` and `cnode`
+fn parse_lsof(output: &str) -> Option<Owner> {
+    let pid = output
+        .lines()
+        .find_map(|l| l.strip_prefix('p'))?
+        .parse()
+        .ok()?;
+    let name = output
+        .lines()
+        .find_map(|l| l.strip_prefix('c'))?
+        .to_string();
+
+    Some(Owner::Process { name, pid })
+}
+
+/// Ports picked instead of taken ones, so later starts publish on them again
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PortRecord {
+    #[serde(skip)]
+    path: PathBuf,
+    #[serde(flatten)]
+    ports: BTreeMap<String, RemappedPort>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RemappedPort {
+    /// Port the service asked for, a changed request drops the record
+    pub requested: u16,
+    pub published: u16,
+}
+
+impl PortRecord {
+    pub fn load(path: &Path) -> Result<PortRecord> {
+        let mut record: PortRecord = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("{} is not valid", path.display()))?,
+            Err(_) => PortRecord::default(),
+        };
+        record.path = path.to_path_buf();
+
+        Ok(record)
+    }
+
+    /// The port to publish on instead of `requested`
+    pub fn published(&self, key: &str, requested: u16) -> u16 {
+        match self.ports.get(key) {
+            Some(port) if port.requested == requested => port.published,
+            _ => requested,
+        }
+    }
+
+    /// Records that `current` was moved to `published`, keeping the original request
+    pub fn remap(&mut self, key: &str, current: u16, published: u16) -> Result<()> {
+        let requested = match self.ports.get(key) {
+            Some(port) if port.published == current => port.requested,
+            _ => current,
+        };
+        self.ports.insert(
+            key.to_string(),
+            RemappedPort {
+                requested,
+                published,
+            },
+        );
+
+        fs::write(&self.path, serde_json::to_string_pretty(&self.ports)?)
+            .with_context(|| format!("Could not write {}", self.path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_lsof_fields() {
+        assert_eq!(
+            parse_lsof("p4211\ncnode\nf23\n"),
+            Some(Owner::Process {
+                name: "node".into(),
+                pid: 4211
+            })
+        );
+        assert_eq!(parse_lsof(""), None);
+    }
+
+    #[test]
+    fn finds_port_after_taken_one() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let taken = listener.local_addr().unwrap().port();
+
+        assert!(!is_free(taken));
+        assert!(next_free(taken).is_some_and(|p| p > taken));
+    }
+
+    #[test]
+    fn remaps_until_request_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ports.json");
+        let mut record = PortRecord::load(&path).unwrap();
+
+        record
+            .remap("og-mongodb/mongodb-local/27017", 27017, 27018)
+            .unwrap();
+        record
+            .remap("og-mongodb/mongodb-local/27017", 27018, 27019)
+            .unwrap();
+        let record = PortRecord::load(&path).unwrap();
+
+        assert_eq!(
+            record.published("og-mongodb/mongodb-local/27017", 27017),
+            27019
+        );
+        assert_eq!(
+            record.published("og-mongodb/mongodb-local/27017", 28017),
+            28017
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::env::{CatalogService, ServiceSettings};
+
+pub const PROJECT_FILE: &str = "og.yaml";
+
+/// Settings a repository checks in as `og.yaml`, shared by everyone working on it
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectConfig {
+    pub sql: ProjectSqlConfig,
+    pub env: Option<EnvConfig>,
+}
+
+/// The local services `og env up` runs for the project
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnvConfig {
+    /// Compose project is named og-<name>, defaults to the directory of `og.yaml`
+    #[serde(default)]
+    pub name: Option<String>,
+    pub services: BTreeMap<CatalogService, Option<ServiceSettings>>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSqlConfig {
+    pub seed: Option<SeedConfig>,
+}
+
+/// Scripts `og sql seed` runs, in the order of their file names
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeedConfig {
+    /// Directory with the `.sql` files, relative to `og.yaml`
+    #[serde(default = "default_scripts")]
+    pub scripts: PathBuf,
+    /// Database the scripts run in, defaults to master so they can create their own
+    pub database: Option<String>,
+}
+
+/// A loaded `og.yaml` and the directory it was found in
+#[derive(Debug)]
+pub struct Project {
+    pub root: PathBuf,
+    pub config: ProjectConfig,
+}
+
+impl Project {
+    /// Looks for `og.yaml` in the current directory and its parents
+    pub fn discover() -> Result<Option<Project>> {
+        Self::find(&std::env::current_dir()?)
+    }
+
+    pub fn find(start: &Path) -> Result<Option<Project>> {
+        let Some(root) = start
+            .ancestors()
+            .find(|dir| dir.join(PROJECT_FILE).is_file())
+        else {
+            return Ok(None);
+        };
+
+        let file = root.join(PROJECT_FILE);
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Could not read {}", file.display()))?;
+        let config = serde_yaml::from_str(&content)
+            .with_context(|| format!("{} is not a valid og project file", file.display()))?;
+
+        Ok(Some(Project {
+            root: root.to_path_buf(),
+            config,
+        }))
+    }
+}
+
+fn default_scripts() -> PathBuf {
+    PathBuf::from("db/init")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_project_in_parents() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROJECT_FILE),
+            "sql:\n  seed:\n    database: Erp\n",
+        )
+        .unwrap();
+        let nested = dir.path().join("src/api");
+        fs::create_dir_all(&nested).unwrap();
+
+        let project = Project::find(&nested).unwrap().unwrap();
+
+        assert_eq!(project.root, dir.path());
+        assert_eq!(
+            project.config.sql.seed,
+            Some(SeedConfig {
+                scripts: PathBuf::from("db/init"),
+                database: Some("Erp".into()),
+            })
+        );
+    }
+
+    #[test]
+    fn reads_env_services() {
+        let config: ProjectConfig =
+            serde_yaml::from_str("env:\n  services:\n    redis:\n    sql:\n      port: 14330\n")
+                .unwrap();
+
+        let env = config.env.unwrap();
+        assert_eq!(env.name, None);
+        assert_eq!(
+            env.services.keys().collect::<Vec<_>>(),
+            [&CatalogService::Sql, &CatalogService::Redis]
+        );
+    }
+
+    #[test]
+    fn empty_project_has_no_seed() {
+        assert_eq!(
+            serde_yaml::from_str::<ProjectConfig>("{}").unwrap(),
+            ProjectConfig::default()
+        );
+    }
+}
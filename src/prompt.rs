@@ -0,0 +1,170 @@
+use dialoguer::{Confirm, MultiSelect, Select};
+use eyre::Result;
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
+
+use crate::error::{ErrorKind, OgError};
+
+#[derive(Debug, PartialEq)]
+struct PromptSettings {
+    assume_yes: bool,
+    interactive: bool,
+}
+
+impl PromptSettings {
+    fn detect(assume_yes: bool, no_input: bool, stdin_is_terminal: bool, ci: Option<&str>) -> Self {
+        let is_ci = ci.is_some_and(|v| v.eq_ignore_ascii_case("true") || v == "1");
+        Self {
+            assume_yes,
+            interactive: !assume_yes && !no_input && stdin_is_terminal && !is_ci,
+        }
+    }
+}
+
+/// Configures prompting from the global `--yes` and `--no-input` flags
+pub fn init(assume_yes: bool, no_input: bool) {
+    let ci = env::var("CI").ok();
+    let _ = SETTINGS.set(PromptSettings::detect(
+        assume_yes,
+        no_input,
+        io::stdin().is_terminal(),
+        ci.as_deref(),
+    ));
+}
+
+fn settings() -> &'static PromptSettings {
+    SETTINGS.get_or_init(|| {
+        let ci = env::var("CI").ok();
+        PromptSettings::detect(false, false, io::stdin().is_terminal(), ci.as_deref())
+    })
+}
+
+/// Whether `--yes` was passed and confirmations and default choices should be accepted
+pub fn assume_yes() -> bool {
+    settings().assume_yes
+}
+
+pub fn is_interactive() -> bool {
+    settings().interactive
+}
+
+/// Fails with a hint to the flag that replaces the prompt if og cannot prompt
+pub fn ensure_interactive(flag_hint: &str) -> Result<()> {
+    if is_interactive() {
+        return Ok(());
+    }
+
+    Err(OgError::msg(
+        ErrorKind::Usage,
+        format!("Input required but og is running non-interactively (no TTY, CI=true, --yes or --no-input). Pass {flag_hint} instead"),
+    )
+    .into())
+}
+
+/// Asks for confirmation, `--yes` answers it without prompting
+pub fn confirm(prompt: &str, default: bool) -> Result<bool> {
+    if assume_yes() {
+        return Ok(true);
+    }
+    ensure_interactive("--yes")?;
+
+    Ok(Confirm::new()
+        .with_prompt(prompt)
+        .default(default)
+        .interact()?)
+}
+
+/// Asks to pick one item, `--yes` picks the default without prompting
+pub fn select<T: ToString>(
+    prompt: &str,
+    items: &[T],
+    default: usize,
+    flag_hint: &str,
+) -> Result<usize> {
+    if assume_yes() {
+        return Ok(default);
+    }
+    ensure_interactive(flag_hint)?;
+
+    Ok(Select::new()
+        .with_prompt(prompt)
+        .items(items)
+        .default(default)
+        .interact()?)
+}
+
+/// Asks to pick items, `--yes` picks the preselected ones and fails if there are none
+pub fn multi_select<T: ToString>(
+    prompt: &str,
+    items: &[T],
+    defaults: &[bool],
+    flag_hint: &str,
+) -> Result<Vec<usize>> {
+    let preselected = preselected(defaults);
+    if assume_yes() && !preselected.is_empty() {
+        return Ok(preselected);
+    }
+    ensure_interactive(flag_hint)?;
+
+    Ok(MultiSelect::new()
+        .with_prompt(prompt)
+        .items(items)
+        .defaults(defaults)
+        .interact()?)
+}
+
+fn preselected(defaults: &[bool]) -> Vec<usize> {
+    defaults
+        .iter()
+        .enumerate()
+        .filter(|(_, selected)| **selected)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+static SETTINGS: OnceLock<PromptSettings> = OnceLock::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interactive_on_terminal() {
+        let settings = PromptSettings::detect(false, false, true, None);
+
+        assert!(settings.interactive);
+        assert!(!settings.assume_yes);
+    }
+
+    #[test]
+    fn non_interactive_without_terminal() {
+        assert!(!PromptSettings::detect(false, false, false, None).interactive);
+    }
+
+    #[test]
+    fn non_interactive_on_ci() {
+        assert!(!PromptSettings::detect(false, false, true, Some("true")).interactive);
+        assert!(PromptSettings::detect(false, false, true, Some("false")).interactive);
+    }
+
+    #[test]
+    fn preselects_default_items() {
+        assert_eq!(preselected(&[true, false, true]), vec![0, 2]);
+        assert!(preselected(&[false, false]).is_empty());
+    }
+
+    #[test]
+    fn non_interactive_with_flags() {
+        assert!(!PromptSettings::detect(false, true, true, None).interactive);
+        assert_eq!(
+            PromptSettings::detect(true, false, true, None),
+            PromptSettings {
+                assume_yes: true,
+                interactive: false
+            }
+        );
+    }
+}
@@ -5,7 +5,10 @@ use json_to_table::json_to_table;
 use log::info;
 use serde_json::Value;
 
-use crate::get_config;
+use crate::{
+    error::{self, ErrorKind, OgError},
+    get_config,
+};
 
 /// Access the search API
 #[derive(Args, Debug)]
@@ -53,7 +56,7 @@ enum Ltr {
 pub struct SearchPlugin;
 
 impl SearchPlugin {
-    pub async fn run(search_command: SearchCommand) -> Result<()> {
+    pub async fn run(search_command: SearchCommand) -> error::Result<()> {
         match search_command.command {
             SearchSubcommands::ApiSearch {
                 environment,
@@ -89,13 +92,20 @@ impl SearchPlugin {
                     take,
                     rewriters,
                 )
-                .await
+                .await?
             }
             SearchSubcommands::EsSearch {
                 environment: _,
                 query: _,
-            } => todo!(),
+            } => {
+                return Err(OgError::msg(
+                    ErrorKind::Other,
+                    "es-search is not implemented yet",
+                ))
+            }
         }
+
+        Ok(())
     }
 
     async fn call_api(
@@ -193,8 +203,7 @@ impl SearchPlugin {
             .header("X-DG-Rewriters", rewriters_str)
             .body(json::stringify(request_body))
             .send()
-            .await
-            .unwrap()
+            .await?
             .json::<Value>()
             .await?;
         info!("{}", json_to_table(&res).to_string());
@@ -0,0 +1,161 @@
+use eyre::Result;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::error::{ErrorKind, OgError};
+
+/// Size of tar headers, entries are padded to it
+const BLOCK_SIZE: u64 = 512;
+
+/// A tar archive with a single file, as the Docker archive API takes it
+pub fn single_file(name: &str, content: &[u8]) -> Result<Vec<u8>> {
+    if name.len() > 100 {
+        Err(OgError::msg(
+            ErrorKind::Usage,
+            format!("{name} is too long for a tar entry"),
+        ))?;
+    }
+
+    let mut header = [0u8; BLOCK_SIZE as usize];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    header[100..108].copy_from_slice(b"0000644\0");
+    header[108..116].copy_from_slice(b"0000000\0");
+    header[116..124].copy_from_slice(b"0000000\0");
+    header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+    header[136..148].copy_from_slice(b"00000000000\0");
+    header[156] = b'0';
+    header[257..265].copy_from_slice(b"ustar\x0000");
+    // The checksum is taken with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+    header[148..156].copy_from_slice(format!("{checksum:06o}\0 ").as_bytes());
+
+    let mut archive = header.to_vec();
+    archive.extend_from_slice(content);
+    archive.resize(archive.len() + padding(content.len() as u64) as usize, 0);
+    // Two empty blocks end the archive
+    archive.resize(archive.len() + 2 * BLOCK_SIZE as usize, 0);
+
+    Ok(archive)
+}
+
+/// Streams the first file of a tar archive into `destination`
+pub async fn extract_file(
+    archive: &mut (impl AsyncRead + Unpin),
+    destination: &mut (impl AsyncWrite + Unpin),
+) -> Result<()> {
+    // Set by a PAX header, e.g. for files over 8 GiB
+    let mut pax_size = None;
+
+    loop {
+        let mut header = [0u8; BLOCK_SIZE as usize];
+        archive.read_exact(&mut header).await?;
+        if header.iter().all(|b| *b == 0) {
+            Err(OgError::msg(
+                ErrorKind::Docker,
+                "The archive contains no file",
+            ))?;
+        }
+        let size = pax_size.take().map_or_else(|| entry_size(&header), Ok)?;
+
+        match header[156] {
+            b'0' | 0 => {
+                let copied = io::copy(&mut (&mut *archive).take(size), destination).await?;
+                if copied != size {
+                    Err(OgError::msg(ErrorKind::Docker, "The archive is truncated"))?;
+                }
+                destination.flush().await?;
+                return Ok(());
+            }
+            b'x' => {
+                let mut records = Vec::new();
+                (&mut *archive).take(size).read_to_end(&mut records).await?;
+                skip(archive, padding(size)).await?;
+                pax_size = pax_record(&String::from_utf8_lossy(&records), "size")
+                    .and_then(|s| s.parse().ok());
+            }
+            _ => skip(archive, size + padding(size)).await?,
+        }
+    }
+}
+
+async fn skip(archive: &mut (impl AsyncRead + Unpin), bytes: u64) -> Result<()> {
+    io::copy(&mut (&mut *archive).take(bytes), &mut io::sink()).await?;
+    Ok(())
+}
+
+fn padding(size: u64) -> u64 {
+    (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE
+}
+
+/// Octal, or base-256 if the high bit is set as GNU tar writes large sizes
+fn entry_size(header: &[u8]) -> Result<u64> {
+    let field = &header[124..136];
+    if field[0] & 0x80 != 0 {
+        return Ok(field[1..]
+            .iter()
+            .fold(0, |size, b| (size << 8) | u64::from(*b)));
+    }
+
+    let octal = String::from_utf8_lossy(field);
+    let octal = octal.trim_matches(|c: char| c == '\0' || c == ' ');
+    u64::from_str_radix(octal, 8).map_err(|_| {
+        OgError::msg(
+            ErrorKind::Docker,
+            format!("The archive has an invalid entry size: {octal}"),
+        )
+        .into()
+    })
+}
+
+/// A value of PAX records, which look like `<length> <key>=<value>\n`
+fn pax_record<'a>(records: &'a str, key: &str) -> Option<&'a str> {
+    records.lines().find_map(|record| {
+        let (_, pair) = record.split_once(' ')?;
+        pair.strip_prefix(key)?.strip_prefix('=')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn extracts_a_written_file() {
+        let content = b"backup".repeat(200);
+        let archive = single_file("restore-1.bak", &content).unwrap();
+
+        let mut extracted = Vec::new();
+        extract_file(&mut archive.as_slice(), &mut extracted)
+            .await
+            .unwrap();
+
+        assert_eq!(archive.len() % 512, 0);
+        assert_eq!(extracted, content);
+    }
+
+    #[tokio::test]
+    async fn skips_pax_headers() {
+        let records = "29 path=a-very-long-name.bak\n10 size=3\n";
+        let mut archive = single_file("PaxHeader", records.as_bytes()).unwrap();
+        archive[156] = b'x';
+        // The checksum isn't verified, only the entry type matters
+        archive.truncate(1024);
+        archive.extend(single_file("a-very-long-n", b"abc").unwrap());
+
+        let mut extracted = Vec::new();
+        extract_file(&mut archive.as_slice(), &mut extracted)
+            .await
+            .unwrap();
+
+        assert_eq!(extracted, b"abc");
+    }
+
+    #[test]
+    fn reads_base_256_sizes() {
+        let mut header = [0u8; 512];
+        header[124] = 0x80;
+        header[131..136].copy_from_slice(&[0x02, 0x00, 0x00, 0x00, 0x01]);
+
+        assert_eq!(entry_size(&header).unwrap(), 8 * 1024 * 1024 * 1024 + 1);
+    }
+}
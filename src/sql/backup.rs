@@ -0,0 +1,368 @@
+use eyre::{Context, ContextCompat, Result};
+use log::info;
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::fs::File;
+
+use super::{
+    instance::SqlInstance,
+    sqlcmd::{quote_name, quote_string, Sqlcmd},
+};
+use crate::{
+    error::{ErrorKind, OgError},
+    output::Table,
+    prompt,
+};
+
+/// Directory inside the container backups are written to and restored from
+const CONTAINER_BACKUP_DIR: &str = "/var/opt/mssql/backup";
+const CONTAINER_DATA_DIR: &str = "/var/opt/mssql/data";
+
+/// A database file in a backup, as listed by `RESTORE FILELISTONLY`
+#[derive(Debug, PartialEq)]
+struct BackupFile {
+    logical_name: String,
+    /// `D` for data, `L` for log, `F` for full-text catalogs and `S` for FILESTREAM
+    file_type: String,
+}
+
+pub async fn backup(
+    instance: &SqlInstance,
+    sqlcmd: &Sqlcmd,
+    database: &str,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    let output = output.unwrap_or_else(|| PathBuf::from(format!("{}.bak", file_stem(database))));
+    let container_file = format!(
+        "{CONTAINER_BACKUP_DIR}/{}-{}.bak",
+        file_stem(database),
+        timestamp()?
+    );
+
+    sqlcmd
+        .run_in_container(&["mkdir", "-p", CONTAINER_BACKUP_DIR])
+        .await?;
+    info!("Backing up database {database}...");
+    sqlcmd
+        .query(
+            &format!(
+                "BACKUP DATABASE {} TO DISK = {} WITH COPY_ONLY, FORMAT, INIT, STATS = 10",
+                quote_name(database),
+                quote_string(&container_file)
+            ),
+            None,
+        )
+        .await?;
+
+    let result = download_file(instance, sqlcmd, &container_file, &output).await;
+    sqlcmd
+        .run_in_container(&["rm", "-f", &container_file])
+        .await?;
+    result?;
+
+    info!("Database {database} backed up to {}", output.display());
+
+    Ok(())
+}
+
+pub async fn restore(
+    instance: &SqlInstance,
+    sqlcmd: &Sqlcmd,
+    file: &Path,
+    target: Option<String>,
+) -> Result<()> {
+    let container_file = format!("{CONTAINER_BACKUP_DIR}/restore-{}.bak", timestamp()?);
+
+    sqlcmd
+        .run_in_container(&["mkdir", "-p", CONTAINER_BACKUP_DIR])
+        .await?;
+    upload_file(instance, sqlcmd, file, &container_file).await?;
+
+    let result = restore_uploaded(sqlcmd, &container_file, target).await;
+    sqlcmd
+        .run_in_container(&["rm", "-f", &container_file])
+        .await?;
+
+    result
+}
+
+async fn restore_uploaded(
+    sqlcmd: &Sqlcmd,
+    container_file: &str,
+    target: Option<String>,
+) -> Result<()> {
+    let target = match target {
+        Some(target) => target,
+        None => {
+            let header = sqlcmd
+                .query(
+                    &format!(
+                        "RESTORE HEADERONLY FROM DISK = {}",
+                        quote_string(container_file)
+                    ),
+                    None,
+                )
+                .await?;
+            first_table(&header)
+                .and_then(|t| column(t, "DatabaseName"))?
+                .into_iter()
+                .next()
+                .context("The backup contains no database")?
+        }
+    };
+
+    let file_list = sqlcmd
+        .query(
+            &format!(
+                "RESTORE FILELISTONLY FROM DISK = {}",
+                quote_string(container_file)
+            ),
+            None,
+        )
+        .await?;
+    let file_list = first_table(&file_list)?;
+    let files: Vec<BackupFile> = column(file_list, "LogicalName")?
+        .into_iter()
+        .zip(column(file_list, "Type")?)
+        .map(|(logical_name, file_type)| BackupFile {
+            logical_name,
+            file_type,
+        })
+        .collect();
+
+    let exists = sqlcmd
+        .query(
+            &format!(
+                "SELECT name FROM sys.databases WHERE name = {}",
+                quote_string(&target)
+            ),
+            None,
+        )
+        .await?
+        .iter()
+        .any(|t| !t.rows.is_empty());
+    if exists
+        && !prompt::confirm(
+            &format!("Database {target} exists, do you want to replace it?"),
+            false,
+        )?
+    {
+        Err(OgError::msg(
+            ErrorKind::UserAbort,
+            format!("Database {target} was not replaced"),
+        ))?;
+    }
+
+    info!("Restoring database {target}...");
+    sqlcmd
+        .query(
+            &restore_statement(&target, container_file, &files, exists),
+            None,
+        )
+        .await?;
+    info!("Database {target} restored");
+
+    Ok(())
+}
+
+/// Moves every file of the backup to a path named after the target, so a copy doesn't collide with the original
+fn restore_statement(
+    target: &str,
+    container_file: &str,
+    files: &[BackupFile],
+    replace: bool,
+) -> String {
+    let stem = file_stem(target);
+    let mut data_files = 0;
+    let mut log_files = 0;
+    let moves: Vec<String> = files
+        .iter()
+        .map(|f| {
+            let physical_name = if f.file_type == "L" {
+                log_files += 1;
+                match log_files {
+                    1 => format!("{stem}_log.ldf"),
+                    n => format!("{stem}_log{n}.ldf"),
+                }
+            } else {
+                data_files += 1;
+                match data_files {
+                    1 => format!("{stem}.mdf"),
+                    n => format!("{stem}_{n}.ndf"),
+                }
+            };
+            format!(
+                "MOVE {} TO {}",
+                quote_string(&f.logical_name),
+                quote_string(&format!("{CONTAINER_DATA_DIR}/{physical_name}"))
+            )
+        })
+        .collect();
+
+    let mut options = moves;
+    if replace {
+        options.push("REPLACE".to_string());
+    }
+    options.push("STATS = 10".to_string());
+
+    format!(
+        "RESTORE DATABASE {} FROM DISK = {} WITH {}",
+        quote_name(target),
+        quote_string(container_file),
+        options.join(", ")
+    )
+}
+
+async fn upload_file(
+    instance: &SqlInstance,
+    sqlcmd: &Sqlcmd,
+    file: &Path,
+    container_file: &str,
+) -> Result<()> {
+    let mut source = File::open(file)
+        .await
+        .with_context(|| format!("Could not open {}", file.display()))
+        .map_err(|e| OgError::new(ErrorKind::Usage, e))?;
+
+    info!(
+        "Copying {} into {}...",
+        file.display(),
+        instance.container_name()
+    );
+    sqlcmd.upload(&mut source, container_file).await
+}
+
+async fn download_file(
+    instance: &SqlInstance,
+    sqlcmd: &Sqlcmd,
+    container_file: &str,
+    output: &Path,
+) -> Result<()> {
+    info!("Copying backup from {}...", instance.container_name());
+    let mut destination = File::create(output)
+        .await
+        .with_context(|| format!("Could not write {}", output.display()))?;
+
+    sqlcmd.download(container_file, &mut destination).await
+}
+
+/// Database names may contain characters that aren't allowed in file names, like `/`
+fn file_stem(database: &str) -> String {
+    database
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn first_table(tables: &[Table]) -> Result<&Table> {
+    tables.first().context("sqlcmd returned no result set")
+}
+
+fn column(table: &Table, name: &str) -> Result<Vec<String>> {
+    let index = table
+        .columns
+        .iter()
+        .position(|c| c == name)
+        .with_context(|| format!("sqlcmd returned no {name} column"))?;
+
+    Ok(table
+        .rows
+        .iter()
+        .filter_map(|r| r.get(index).cloned())
+        .collect())
+}
+
+fn timestamp() -> Result<u64> {
+    Ok(SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)?
+        .as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_moves_files_to_target_names() {
+        let files = vec![
+            BackupFile {
+                logical_name: "Erp".into(),
+                file_type: "D".into(),
+            },
+            BackupFile {
+                logical_name: "Erp_Archive".into(),
+                file_type: "D".into(),
+            },
+            BackupFile {
+                logical_name: "Erp_log".into(),
+                file_type: "L".into(),
+            },
+        ];
+
+        assert_eq!(
+            restore_statement(
+                "ErpCopy",
+                "/var/opt/mssql/backup/restore-1.bak",
+                &files,
+                false
+            ),
+            "RESTORE DATABASE [ErpCopy] FROM DISK = N'/var/opt/mssql/backup/restore-1.bak' WITH \
+             MOVE N'Erp' TO N'/var/opt/mssql/data/ErpCopy.mdf', \
+             MOVE N'Erp_Archive' TO N'/var/opt/mssql/data/ErpCopy_2.ndf', \
+             MOVE N'Erp_log' TO N'/var/opt/mssql/data/ErpCopy_log.ldf', \
+             STATS = 10"
+        );
+    }
+
+    #[test]
+    fn restore_names_files_safely() {
+        let files = vec![BackupFile {
+            logical_name: "Erp".into(),
+            file_type: "D".into(),
+        }];
+
+        assert_eq!(
+            restore_statement("../Erp Copy", "/backup.bak", &files, false),
+            "RESTORE DATABASE [../Erp Copy] FROM DISK = N'/backup.bak' WITH \
+             MOVE N'Erp' TO N'/var/opt/mssql/data/.._Erp_Copy.mdf', \
+             STATS = 10"
+        );
+    }
+
+    #[test]
+    fn restore_replaces_existing_database() {
+        let files = vec![BackupFile {
+            logical_name: "Erp".into(),
+            file_type: "D".into(),
+        }];
+
+        assert!(
+            restore_statement("Erp", "/backup.bak", &files, true).ends_with("REPLACE, STATS = 10")
+        );
+    }
+
+    #[test]
+    fn finds_columns_by_name() {
+        let table = Table {
+            columns: vec!["LogicalName".into(), "Type".into()],
+            rows: vec![vec!["Erp".into(), "D".into()]],
+        };
+
+        assert_eq!(column(&table, "Type").unwrap(), vec!["D".to_string()]);
+        assert!(column(&table, "Missing").is_err());
+    }
+
+    #[test]
+    fn backup_files_are_named_safely() {
+        assert_eq!(file_stem("shop"), "shop");
+        assert_eq!(file_stem("shop/../orders db"), "shop_.._orders_db");
+    }
+}
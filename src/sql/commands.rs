@@ -0,0 +1,163 @@
+use clap::{Args, Subcommand};
+use std::{path::PathBuf, time::Duration};
+
+use crate::{connection_string::ConnectionStringArgs, duration::parse_duration};
+
+/// Run a SQL server inside a docker container
+#[derive(Args, Debug)]
+pub struct SqlCommand {
+    #[command(subcommand)]
+    pub command: SqlSubcommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SqlSubcommands {
+    Start(StartArgs),
+    Stop(InstanceArgs),
+    Remove(RemoveArgs),
+    /// Recreate the container on a fresh volume, deleting all databases
+    Reset(InstanceArgs),
+    /// Show the container's image, health, port, volume and connection string
+    Status(InstanceArgs),
+    /// Print the SQL Server logs
+    Logs(LogsArgs),
+    /// List all SQL Server containers managed by og
+    List,
+    /// Run a query and print its result sets
+    Query(QueryArgs),
+    /// Run a script file, batches are separated by GO
+    Exec(ExecArgs),
+    /// Run queries interactively
+    Shell(ConnectionArgs),
+    /// Back up a database to a .bak file
+    Backup(BackupArgs),
+    /// Restore a database from a .bak file
+    Restore(RestoreArgs),
+    /// Run the .sql scripts of the project that weren't applied yet
+    Seed(SeedArgs),
+    /// Print a connection string for the instance
+    ConnectionString(SqlConnectionStringArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct InstanceArgs {
+    /// Name of the instance, the container is called mssql-<name> [default: local]
+    #[arg(short, long)]
+    pub name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct StartArgs {
+    #[command(flatten)]
+    pub instance: InstanceArgs,
+    /// Host port SQL Server is published on [default: 1433]
+    #[arg(short, long)]
+    pub port: Option<u16>,
+    /// SQL Server image to run
+    #[arg(short, long)]
+    pub image: Option<String>,
+    /// Wait until SQL Server accepts logins
+    #[arg(short, long)]
+    pub wait: bool,
+    /// How long to wait, e.g. 90s or 2m
+    #[arg(long, value_parser = parse_duration, default_value = "60s", requires = "wait")]
+    pub timeout: Duration,
+    /// Pull the image and recreate the container, the databases are kept
+    #[arg(long)]
+    pub recreate: bool,
+    /// Don't run the seed scripts of og.yaml when the container is created
+    #[arg(long)]
+    pub no_seed: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct RemoveArgs {
+    #[command(flatten)]
+    pub instance: InstanceArgs,
+    /// Also delete the data volume with all databases
+    #[arg(short, long)]
+    pub volumes: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct ConnectionArgs {
+    #[command(flatten)]
+    pub instance: InstanceArgs,
+    /// Database to connect to [default: master]
+    #[arg(short, long)]
+    pub database: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    /// The SQL to run, e.g. "SELECT name FROM sys.databases"
+    pub sql: String,
+}
+
+#[derive(Args, Debug)]
+pub struct ExecArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    /// Path of the .sql script
+    #[arg(short, long)]
+    pub file: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct BackupArgs {
+    #[command(flatten)]
+    pub instance: InstanceArgs,
+    /// Name of the database
+    pub database: String,
+    /// Path of the backup file [default: <database>.bak]
+    #[arg(short = 'o', long = "file")]
+    pub file: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct RestoreArgs {
+    #[command(flatten)]
+    pub instance: InstanceArgs,
+    /// Path of the backup file
+    pub file: PathBuf,
+    /// Restore under another name, e.g. to keep the original database [default: name in the backup]
+    #[arg(long = "as", value_name = "NAME")]
+    pub target: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct LogsArgs {
+    #[command(flatten)]
+    pub instance: InstanceArgs,
+    /// Keep printing new log lines
+    #[arg(short, long)]
+    pub follow: bool,
+    /// Only logs of the last duration, e.g. 10m
+    #[arg(long, value_parser = parse_duration)]
+    pub since: Option<Duration>,
+    /// Only the last lines
+    #[arg(long)]
+    pub tail: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+pub struct SqlConnectionStringArgs {
+    #[command(flatten)]
+    pub connection: ConnectionArgs,
+    #[command(flatten)]
+    pub connection_string: ConnectionStringArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct SeedArgs {
+    #[command(flatten)]
+    pub instance: InstanceArgs,
+    /// Directory with the scripts [default: sql.seed.scripts of og.yaml]
+    #[arg(long)]
+    pub dir: Option<PathBuf>,
+    /// Database the scripts run in [default: sql.seed.database of og.yaml or master]
+    #[arg(short, long)]
+    pub database: Option<String>,
+}
@@ -0,0 +1,12 @@
+use super::SqlPlugin;
+use crate::{
+    container_runtime::runtime,
+    doctor::{DoctorFailure, DoctorSuccess},
+    plugin::Plugin,
+};
+
+impl Plugin for SqlPlugin {
+    fn doctor(&self) -> Vec<Result<DoctorSuccess, DoctorFailure>> {
+        vec![runtime().doctor("Sql")]
+    }
+}
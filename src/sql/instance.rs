@@ -0,0 +1,193 @@
+use std::{collections::HashMap, env::consts::ARCH};
+
+use crate::{
+    config::SqlConfig,
+    error::{ErrorKind, OgError, Result},
+};
+
+pub const DEFAULT_NAME: &str = "local";
+/// SQL Server for Linux only ships x86_64 images
+pub const X86_64_IMAGE: &str = "mcr.microsoft.com/mssql/server:2022-CU14-ubuntu-22.04";
+/// Azure SQL Edge is deprecated, but the only SQL Server that runs natively on ARM
+pub const AARCH64_IMAGE: &str = "mcr.microsoft.com/azure-sql-edge:1.0.7";
+pub const DEFAULT_PORT: u16 = 1433;
+/// Port SQL Server listens on inside the container
+pub const CONTAINER_PORT: u16 = 1433;
+/// Login og creates the containers with, its password is `sql_password` from the config
+pub const SA_USER: &str = "sa";
+
+const CONTAINER_PREFIX: &str = "mssql-";
+/// Volume of the default instance, kept from before instances could be named
+const DEFAULT_VOLUME: &str = "sql-data";
+
+pub const SERVICE_LABEL: &str = "og-cli.service";
+pub const INSTANCE_LABEL: &str = "og-cli.instance";
+pub const SERVICE: &str = "sql";
+
+/// A SQL Server container managed by og
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlInstance {
+    pub name: String,
+    pub port: u16,
+    pub image: String,
+}
+
+impl SqlInstance {
+    /// Resolves the instance from the command line, falling back to the config and built-in defaults
+    pub fn resolve(
+        config: &SqlConfig,
+        name: Option<String>,
+        port: Option<u16>,
+        image: Option<String>,
+    ) -> Result<Self> {
+        let name = name
+            .or_else(|| config.name.clone())
+            .unwrap_or_else(|| DEFAULT_NAME.to_string());
+        validate_name(&name)?;
+
+        Ok(Self {
+            name,
+            port: port.or(config.port).unwrap_or(DEFAULT_PORT),
+            image: image.unwrap_or_else(|| configured_image(config, ARCH)),
+        })
+    }
+
+    pub fn container_name(&self) -> String {
+        format!("{CONTAINER_PREFIX}{}", self.name)
+    }
+
+    pub fn volume_name(&self) -> String {
+        if self.name == DEFAULT_NAME {
+            DEFAULT_VOLUME.to_string()
+        } else {
+            format!("{DEFAULT_VOLUME}-{}", self.name)
+        }
+    }
+
+    pub fn labels(&self) -> HashMap<&'static str, &str> {
+        HashMap::from([
+            (SERVICE_LABEL, SERVICE),
+            (INSTANCE_LABEL, self.name.as_str()),
+        ])
+    }
+}
+
+/// Image from the config for the architecture, falling back to the pinned default
+fn configured_image(config: &SqlConfig, arch: &str) -> String {
+    config
+        .image
+        .clone()
+        .or_else(|| config.images.get(arch).cloned())
+        .unwrap_or_else(|| default_image(arch).to_string())
+}
+
+pub fn default_image(arch: &str) -> &'static str {
+    match arch {
+        "aarch64" => AARCH64_IMAGE,
+        _ => X86_64_IMAGE,
+    }
+}
+
+/// Instance name of an og container, also for containers created before they were labeled
+pub fn instance_name(
+    container_name: &str,
+    labels: Option<&HashMap<String, String>>,
+) -> Option<String> {
+    labels
+        .and_then(|l| l.get(INSTANCE_LABEL).cloned())
+        .or_else(|| {
+            container_name
+                .trim_start_matches('/')
+                .strip_prefix(CONTAINER_PREFIX)
+                .map(|n| n.to_string())
+        })
+}
+
+/// Docker only allows `[a-zA-Z0-9][a-zA-Z0-9_.-]` in container and volume names
+fn validate_name(name: &str) -> Result<()> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+
+    if !valid {
+        return Err(OgError::msg(
+            ErrorKind::Usage,
+            format!("Invalid instance name '{name}', use letters, digits, '_', '.' and '-'"),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_instance_keeps_existing_names() {
+        let instance = SqlInstance::resolve(&SqlConfig::default(), None, None, None).unwrap();
+
+        assert_eq!(instance.container_name(), "mssql-local");
+        assert_eq!(instance.volume_name(), "sql-data");
+        assert_eq!(instance.port, 1433);
+        assert_eq!(instance.image, default_image(ARCH));
+    }
+
+    #[test]
+    fn flags_override_config() {
+        let config = SqlConfig {
+            name: Some("shop".into()),
+            port: Some(11433),
+            image: Some("mcr.microsoft.com/mssql/server:2022-latest".into()),
+            ..Default::default()
+        };
+
+        let instance =
+            SqlInstance::resolve(&config, Some("erp".into()), Some(14330), None).unwrap();
+
+        assert_eq!(instance.container_name(), "mssql-erp");
+        assert_eq!(instance.volume_name(), "sql-data-erp");
+        assert_eq!(instance.port, 14330);
+        assert_eq!(instance.image, "mcr.microsoft.com/mssql/server:2022-latest");
+    }
+
+    #[test]
+    fn picks_image_per_architecture() {
+        let mut config = SqlConfig {
+            images: HashMap::from([("x86_64".into(), "registry.local/mssql:2019".into())]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            configured_image(&config, "x86_64"),
+            "registry.local/mssql:2019"
+        );
+        assert_eq!(configured_image(&config, "aarch64"), AARCH64_IMAGE);
+
+        config.image = Some("registry.local/mssql:2022".into());
+        assert_eq!(
+            configured_image(&config, "aarch64"),
+            "registry.local/mssql:2022"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        let result = SqlInstance::resolve(&SqlConfig::default(), Some("my db".into()), None, None);
+
+        assert_eq!(result.unwrap_err().kind(), ErrorKind::Usage);
+    }
+
+    #[test]
+    fn finds_instance_names() {
+        let labels = HashMap::from([(INSTANCE_LABEL.to_string(), "erp".to_string())]);
+
+        assert_eq!(
+            instance_name("/mssql-erp", Some(&labels)),
+            Some("erp".into())
+        );
+        assert_eq!(instance_name("/mssql-local", None), Some("local".into()));
+        assert_eq!(instance_name("/postgres", None), None);
+    }
+}
@@ -0,0 +1,14 @@
+mod archive;
+mod backup;
+mod commands;
+mod doctor;
+mod instance;
+mod seed;
+mod sql;
+mod sqlcmd;
+mod status;
+
+pub use commands::SqlCommand;
+pub use instance::{default_image, SA_USER};
+pub use sql::SqlPlugin;
+pub use sqlcmd::healthcheck;
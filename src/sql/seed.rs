@@ -0,0 +1,215 @@
+use eyre::{Context, Result};
+use log::{info, warn};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::sqlcmd::{quote_string, Sqlcmd};
+use crate::{
+    error::{ErrorKind, OgError},
+    project::{Project, PROJECT_FILE},
+};
+
+/// The history lives in master, the scripts usually create the other databases
+const HISTORY_DATABASE: &str = "master";
+const CREATE_HISTORY: &str = "IF OBJECT_ID(N'dbo.og_seed_history') IS NULL
+CREATE TABLE dbo.og_seed_history (
+    script NVARCHAR(260) NOT NULL PRIMARY KEY,
+    checksum CHAR(64) NOT NULL,
+    applied_at DATETIME2 NOT NULL DEFAULT SYSUTCDATETIME()
+)";
+
+/// Where the scripts come from, flags override the project's `og.yaml`
+#[derive(Debug, PartialEq)]
+pub struct SeedSettings {
+    pub dir: PathBuf,
+    pub database: Option<String>,
+}
+
+impl SeedSettings {
+    pub fn resolve(dir: Option<PathBuf>, database: Option<String>) -> Result<Option<Self>> {
+        let seed = Project::discover()?.and_then(|p| {
+            p.config
+                .sql
+                .seed
+                .map(|s| (p.root.join(s.scripts), s.database))
+        });
+
+        let Some(dir) = dir.or_else(|| seed.as_ref().map(|(dir, _)| dir.clone())) else {
+            return Ok(None);
+        };
+
+        Ok(Some(Self {
+            dir,
+            database: database.or(seed.and_then(|(_, database)| database)),
+        }))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Script {
+    name: String,
+    content: String,
+    checksum: String,
+}
+
+/// Runs the scripts that weren't applied yet and records them in `og_seed_history`
+pub async fn seed(sqlcmd: &Sqlcmd, settings: &SeedSettings) -> Result<()> {
+    let scripts = find_scripts(&settings.dir)?;
+    if scripts.is_empty() {
+        info!("No .sql scripts in {}", settings.dir.display());
+        return Ok(());
+    }
+
+    sqlcmd.query(CREATE_HISTORY, Some(HISTORY_DATABASE)).await?;
+    let applied: HashMap<String, String> = sqlcmd
+        .query(
+            "SELECT script, checksum FROM dbo.og_seed_history",
+            Some(HISTORY_DATABASE),
+        )
+        .await?
+        .into_iter()
+        .flat_map(|t| t.rows)
+        .filter_map(|r| Some((r.first()?.clone(), r.get(1)?.clone())))
+        .collect();
+
+    let pending = pending_scripts(&scripts, &applied);
+    for script in &pending {
+        info!("Applying {}...", script.name);
+        sqlcmd
+            .query(&script.content, settings.database.as_deref())
+            .await
+            .wrap_err_with(|| format!("Seed script {} failed", script.name))?;
+        sqlcmd
+            .query(
+                &format!(
+                    "INSERT INTO dbo.og_seed_history (script, checksum) VALUES ({}, {})",
+                    quote_string(&script.name),
+                    quote_string(&script.checksum)
+                ),
+                Some(HISTORY_DATABASE),
+            )
+            .await?;
+    }
+
+    info!(
+        "Applied {} seed scripts, {} were applied before",
+        pending.len(),
+        scripts.len() - pending.len()
+    );
+
+    Ok(())
+}
+
+/// Applied scripts never run again, a changed one only gets a warning
+fn pending_scripts<'a>(
+    scripts: &'a [Script],
+    applied: &HashMap<String, String>,
+) -> Vec<&'a Script> {
+    scripts
+        .iter()
+        .filter(|s| match applied.get(&s.name) {
+            Some(checksum) if *checksum != s.checksum => {
+                warn!(
+                    "{} changed since it was applied, it won't run again, add a new script instead",
+                    s.name
+                );
+                false
+            }
+            Some(_) => false,
+            None => true,
+        })
+        .collect()
+}
+
+/// The `.sql` files of the directory, ordered by name like `001-schema.sql`
+fn find_scripts(dir: &Path) -> Result<Vec<Script>> {
+    if !dir.is_dir() {
+        Err(OgError::msg(
+            ErrorKind::Usage,
+            format!(
+                "Seed directory {} doesn't exist, check --dir or sql.seed.scripts in {PROJECT_FILE}",
+                dir.display()
+            ),
+        ))?;
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|p| p.is_file() && p.extension().is_some_and(|e| e == "sql"));
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read {}", path.display()))?;
+            Ok(Script {
+                name: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                checksum: Sha256::digest(&content)
+                    .iter()
+                    .map(|b| format!("{b:02x}"))
+                    .collect(),
+                content,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_scripts_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("002-data.sql"), "INSERT 2").unwrap();
+        fs::write(dir.path().join("001-schema.sql"), "CREATE 1").unwrap();
+        fs::write(dir.path().join("README.md"), "docs").unwrap();
+
+        let scripts = find_scripts(dir.path()).unwrap();
+
+        assert_eq!(
+            scripts.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(),
+            ["001-schema.sql", "002-data.sql"]
+        );
+        assert_eq!(scripts[0].content, "CREATE 1");
+        assert_eq!(scripts[0].checksum.len(), 64);
+    }
+
+    #[test]
+    fn skips_applied_scripts() {
+        let script = |name: &str, checksum: &str| Script {
+            name: name.into(),
+            content: String::new(),
+            checksum: checksum.into(),
+        };
+        let scripts = [
+            script("001.sql", "a"),
+            script("002.sql", "b"),
+            script("003.sql", "c"),
+        ];
+        let applied = HashMap::from([
+            ("001.sql".to_string(), "a".to_string()),
+            ("002.sql".to_string(), "changed".to_string()),
+        ]);
+
+        let pending = pending_scripts(&scripts, &applied);
+
+        assert_eq!(pending, [&scripts[2]]);
+    }
+
+    #[test]
+    fn missing_directory_is_a_usage_error() {
+        let error = find_scripts(Path::new("/does/not/exist")).unwrap_err();
+
+        assert_eq!(OgError::from(error).kind(), ErrorKind::Usage);
+    }
+}
@@ -0,0 +1,723 @@
+use bollard::{
+    container::{
+        Config, CreateContainerOptions, ListContainersOptions, RestartContainerOptions,
+        StartContainerOptions,
+    },
+    errors::Error as DockerError,
+    models::{
+        ContainerStateStatusEnum,
+        ContainerStateStatusEnum::{EMPTY, EXITED, RUNNING},
+        HostConfig, PortBinding,
+    },
+    Docker,
+};
+use eyre::Context;
+use eyre::Result;
+use log::{debug, error, info, warn};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use super::{
+    backup::{backup, restore},
+    commands::{SqlCommand, SqlSubcommands},
+    instance::{instance_name, SqlInstance, CONTAINER_PORT, SA_USER, SERVICE, SERVICE_LABEL},
+    seed::{seed, SeedSettings},
+    sqlcmd::{healthcheck, Sqlcmd},
+    status::{host_port, status},
+};
+use crate::{
+    common_docker::{logs, pull_image},
+    connection_string::{self, ConnectionFormat, Endpoint},
+    container_runtime,
+    error::{self, ErrorKind, OgError},
+    get_config, output, ports,
+    project::PROJECT_FILE,
+    prompt,
+};
+
+pub struct SqlPlugin;
+
+const WAIT_INTERVAL: Duration = Duration::from_secs(2);
+/// A new container needs to initialize its databases before it can be seeded
+const SEED_TIMEOUT: Duration = Duration::from_secs(120);
+
+impl SqlPlugin {
+    pub async fn run(cli: SqlCommand) -> error::Result<()> {
+        let docker = init_docker().await?;
+        let config = &get_config().sql;
+
+        match cli.command {
+            SqlSubcommands::Start(args) => {
+                let mut instance =
+                    SqlInstance::resolve(config, args.instance.name, args.port, args.image)?;
+                let status = get_container_status(docker.clone(), &instance).await?;
+                let requested_port = args.port.or(config.port);
+                if args.recreate && status != EMPTY {
+                    if requested_port.is_none() {
+                        keep_published_port(&docker, &mut instance).await;
+                    }
+                    instance.port =
+                        ports::choose(&docker, instance.port, &instance.container_name()).await?;
+                    recreate(docker.clone(), &instance, status).await?;
+                } else {
+                    if status == EMPTY {
+                        instance.port =
+                            ports::choose(&docker, instance.port, &instance.container_name())
+                                .await?;
+                    } else {
+                        warn_on_changed_settings(docker.clone(), &instance, requested_port).await?;
+                        keep_published_port(&docker, &mut instance).await;
+                    }
+                    if status == RUNNING {
+                        info!(
+                            "Container {} is already running, nothing to do.",
+                            instance.container_name()
+                        );
+                    } else {
+                        start(docker.clone(), &instance, status).await?;
+                        if status == EMPTY && !args.no_seed {
+                            seed_new_container(docker.clone(), &instance).await?;
+                        }
+                    }
+                }
+                if args.wait {
+                    wait_until_ready(docker, &instance, args.timeout).await?;
+                }
+            }
+            SqlSubcommands::Stop(args) => {
+                let instance = SqlInstance::resolve(config, args.name, None, None)?;
+                let status = get_container_status(docker.clone(), &instance).await?;
+                if status == EXITED {
+                    info!(
+                        "Container {} is already stopped, nothing to do.",
+                        instance.container_name()
+                    );
+                    return Ok(());
+                }
+                stop(docker, &instance).await?;
+            }
+            SqlSubcommands::Remove(args) => {
+                let instance = SqlInstance::resolve(config, args.instance.name, None, None)?;
+                let status = get_container_status(docker.clone(), &instance).await?;
+                if status == EMPTY && !args.volumes {
+                    info!(
+                        "Container {} doesn't exist, nothing to remove.",
+                        instance.container_name()
+                    );
+                    return Ok(());
+                }
+                if args.volumes {
+                    confirm_data_loss(docker.clone(), &instance, &status).await?;
+                }
+
+                stop_and_remove(docker.clone(), &instance, status).await?;
+                if args.volumes {
+                    remove_volume(&docker, &instance).await?;
+                }
+            }
+            SqlSubcommands::Reset(args) => {
+                let mut instance = SqlInstance::resolve(config, args.name, None, None)?;
+                let status = get_container_status(docker.clone(), &instance).await?;
+                keep_published_port(&docker, &mut instance).await;
+                confirm_data_loss(docker.clone(), &instance, &status).await?;
+
+                stop_and_remove(docker.clone(), &instance, status).await?;
+                remove_volume(&docker, &instance).await?;
+                create_and_run_container(docker.clone(), &instance).await?;
+                seed_new_container(docker, &instance).await?;
+            }
+            SqlSubcommands::Status(args) => {
+                let instance = SqlInstance::resolve(config, args.name, None, None)?;
+                status(&docker, &instance).await?;
+            }
+            SqlSubcommands::Logs(args) => {
+                let instance = SqlInstance::resolve(config, args.instance.name, None, None)?;
+                logs(
+                    &docker,
+                    &instance.container_name(),
+                    args.follow,
+                    args.since,
+                    args.tail,
+                )
+                .await?;
+            }
+            SqlSubcommands::List => list(docker).await?,
+            SqlSubcommands::Query(args) => {
+                let instance =
+                    SqlInstance::resolve(config, args.connection.instance.name, None, None)?;
+                let sqlcmd = connect(docker, &instance).await?;
+                let tables = sqlcmd
+                    .query(&args.sql, args.connection.database.as_deref())
+                    .await?;
+                output::print_tables(&tables);
+            }
+            SqlSubcommands::Exec(args) => {
+                let instance =
+                    SqlInstance::resolve(config, args.connection.instance.name, None, None)?;
+                let script = fs::read_to_string(&args.file)
+                    .with_context(|| format!("Could not read {}", args.file.display()))
+                    .map_err(|e| OgError::new(ErrorKind::Usage, e))?;
+                let sqlcmd = connect(docker, &instance).await?;
+                let tables = sqlcmd
+                    .query(&script, args.connection.database.as_deref())
+                    .await?;
+                if !tables.is_empty() {
+                    output::print_tables(&tables);
+                }
+            }
+            SqlSubcommands::Shell(args) => {
+                let instance = SqlInstance::resolve(config, args.instance.name, None, None)?;
+                prompt::ensure_interactive("a statement to og sql query")?;
+                let sqlcmd = connect(docker, &instance).await?;
+                shell(&sqlcmd, &instance, args.database.as_deref()).await?;
+            }
+            SqlSubcommands::Backup(args) => {
+                let instance = SqlInstance::resolve(config, args.instance.name, None, None)?;
+                let sqlcmd = connect(docker.clone(), &instance).await?;
+                backup(&instance, &sqlcmd, &args.database, args.file).await?;
+            }
+            SqlSubcommands::Restore(args) => {
+                let instance = SqlInstance::resolve(config, args.instance.name, None, None)?;
+                let sqlcmd = connect(docker.clone(), &instance).await?;
+                restore(&instance, &sqlcmd, &args.file, args.target).await?;
+            }
+            SqlSubcommands::Seed(args) => {
+                let instance = SqlInstance::resolve(config, args.instance.name, None, None)?;
+                let Some(settings) = SeedSettings::resolve(args.dir, args.database)? else {
+                    Err(OgError::msg(
+                        ErrorKind::Usage,
+                        format!("No seed scripts configured, add sql.seed to {PROJECT_FILE} or pass --dir"),
+                    ))?
+                };
+                let sqlcmd = connect(docker, &instance).await?;
+                seed(&sqlcmd, &settings).await?;
+            }
+            SqlSubcommands::ConnectionString(args) => {
+                let instance =
+                    SqlInstance::resolve(config, args.connection.instance.name, None, None)?;
+                let endpoint = Endpoint {
+                    host: "localhost",
+                    port: published_port(&docker, &instance).await?,
+                    user: SA_USER,
+                    password: &get_config().sql_password,
+                    database: args.connection.database.as_deref(),
+                };
+                let format = args
+                    .connection_string
+                    .format
+                    .unwrap_or(ConnectionFormat::Ado);
+                connection_string::print(
+                    &connection_string::sql_server(format, &endpoint),
+                    args.connection_string.copy,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Polls until a login as SA succeeds, SQL Server needs a while after the container started
+async fn wait_until_ready(docker: Docker, instance: &SqlInstance, timeout: Duration) -> Result<()> {
+    let started = Instant::now();
+    let sqlcmd = Sqlcmd::connect(docker.clone(), instance).await?;
+    info!(
+        "Waiting up to {}s for {} to accept logins...",
+        timeout.as_secs(),
+        instance.container_name()
+    );
+
+    loop {
+        match sqlcmd.query("SELECT 1", None).await {
+            Ok(_) => {
+                info!(
+                    "{} accepts logins after {}s",
+                    instance.container_name(),
+                    started.elapsed().as_secs()
+                );
+                return Ok(());
+            }
+            Err(error) => debug!("Login failed: {error}"),
+        }
+
+        if get_container_status(docker.clone(), instance).await? != RUNNING {
+            Err(OgError::msg(
+                ErrorKind::Docker,
+                format!(
+                    "Container {0} stopped while starting, check its logs with: docker logs {0}",
+                    instance.container_name()
+                ),
+            ))?;
+        }
+        if started.elapsed() >= timeout {
+            Err(OgError::msg(
+                ErrorKind::Docker,
+                format!(
+                    "{} did not accept logins within {}s",
+                    instance.container_name(),
+                    timeout.as_secs()
+                ),
+            ))?;
+        }
+
+        info!(
+            "Still waiting for {} ({}s)...",
+            instance.container_name(),
+            started.elapsed().as_secs()
+        );
+        tokio::time::sleep(WAIT_INTERVAL).await;
+    }
+}
+
+/// Runs the seed scripts of the project og is started in against a container that was just created
+async fn seed_new_container(docker: Docker, instance: &SqlInstance) -> Result<()> {
+    let Some(settings) = SeedSettings::resolve(None, None)? else {
+        return Ok(());
+    };
+
+    info!(
+        "Seeding {} from {}...",
+        instance.container_name(),
+        settings.dir.display()
+    );
+    wait_until_ready(docker.clone(), instance, SEED_TIMEOUT).await?;
+    let sqlcmd = Sqlcmd::connect(docker, instance).await?;
+    seed(&sqlcmd, &settings).await
+}
+
+async fn connect(docker: Docker, instance: &SqlInstance) -> Result<Sqlcmd> {
+    if get_container_status(docker.clone(), instance).await? != RUNNING {
+        Err(OgError::msg(
+            ErrorKind::Usage,
+            format!(
+                "Container {} is not running, start it with: og sql start --name {}",
+                instance.container_name(),
+                instance.name
+            ),
+        ))?;
+    }
+
+    Sqlcmd::connect(docker, instance).await
+}
+
+/// Reads statements until a line ends with `;` or is `GO`, each one runs in its own session
+async fn shell(sqlcmd: &Sqlcmd, instance: &SqlInstance, database: Option<&str>) -> Result<()> {
+    info!(
+        "Connected to {}, end statements with ';' or a GO line, leave with exit",
+        instance.container_name()
+    );
+
+    let mut batch = String::new();
+    loop {
+        print!("{}> ", if batch.is_empty() { "sql" } else { "..." });
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if io::stdin().read_line(&mut line)? == 0 {
+            break;
+        }
+
+        let trimmed = line.trim();
+        if batch.is_empty() && matches!(trimmed, "exit" | "quit") {
+            break;
+        }
+        if !trimmed.eq_ignore_ascii_case("go") {
+            batch.push_str(&line);
+            if !trimmed.ends_with(';') {
+                continue;
+            }
+        }
+
+        match sqlcmd.query(&batch, database).await {
+            Ok(tables) if !tables.is_empty() => output::print_tables(&tables),
+            Ok(_) => {}
+            Err(e) => error!("{e}"),
+        }
+        batch.clear();
+    }
+
+    Ok(())
+}
+
+async fn list(docker: Docker) -> Result<()> {
+    let label = format!("{SERVICE_LABEL}={SERVICE}");
+    let options = ListContainersOptions {
+        all: true,
+        filters: HashMap::from([("label", vec![label.as_str()])]),
+        ..Default::default()
+    };
+    let mut containers = docker.list_containers(Some(options)).await?;
+
+    // The default instance was created without labels by older versions
+    let legacy_options = ListContainersOptions {
+        all: true,
+        filters: HashMap::from([("name", vec!["^/mssql-local$"])]),
+        ..Default::default()
+    };
+    for container in docker.list_containers(Some(legacy_options)).await? {
+        if !containers.iter().any(|c| c.id == container.id) {
+            containers.push(container);
+        }
+    }
+
+    if containers.is_empty() {
+        info!("No SQL instances found, create one with: og sql start");
+        return Ok(());
+    }
+
+    for container in containers {
+        let container_name = container
+            .names
+            .as_ref()
+            .and_then(|n| n.first())
+            .map(|n| n.trim_start_matches('/').to_string())
+            .unwrap_or_default();
+        let port = container
+            .ports
+            .iter()
+            .flatten()
+            .find_map(|p| p.public_port)
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        info!(
+            "{}: container {}, {}, port {}, image {}",
+            instance_name(&container_name, container.labels.as_ref()).unwrap_or_default(),
+            container_name,
+            container.state.unwrap_or_default(),
+            port,
+            container.image.unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
+
+async fn remove(docker: Docker, instance: &SqlInstance) -> Result<()> {
+    let container_name = instance.container_name();
+    info!("Removing container {}...", container_name);
+    docker.remove_container(&container_name, None).await?;
+    info!("Container {} removed ", container_name);
+    Ok(())
+}
+
+async fn stop_and_remove(
+    docker: Docker,
+    instance: &SqlInstance,
+    status: ContainerStateStatusEnum,
+) -> Result<()> {
+    if status == EMPTY {
+        return Ok(());
+    }
+    if status == RUNNING {
+        info!(
+            "Container {} is running, it must be stopped first.",
+            instance.container_name()
+        );
+        stop(docker.clone(), instance).await?;
+    }
+
+    remove(docker, instance).await
+}
+
+async fn remove_volume(docker: &Docker, instance: &SqlInstance) -> Result<()> {
+    let volume_name = instance.volume_name();
+    match docker.remove_volume(&volume_name, None).await {
+        Ok(()) => info!("Volume {volume_name} removed"),
+        Err(DockerError::DockerResponseServerError {
+            status_code: 404, ..
+        }) => info!("Volume {volume_name} doesn't exist, nothing to remove."),
+        Err(error) => return Err(error.into()),
+    }
+    Ok(())
+}
+
+/// Pulls the image before touching the container, so a failed pull leaves the old one in place
+async fn recreate(
+    docker: Docker,
+    instance: &SqlInstance,
+    status: ContainerStateStatusEnum,
+) -> Result<()> {
+    pull_image(&docker, &instance.image).await?;
+    info!(
+        "Recreating container {}, the databases in volume {} are kept...",
+        instance.container_name(),
+        instance.volume_name()
+    );
+    stop_and_remove(docker.clone(), instance, status).await?;
+    run_container(docker, instance).await
+}
+
+/// The port the container is published on, which may differ from the configured one
+async fn published_port(docker: &Docker, instance: &SqlInstance) -> Result<u16> {
+    let inspect = match docker
+        .inspect_container(&instance.container_name(), None)
+        .await
+    {
+        Ok(inspect) => inspect,
+        Err(DockerError::DockerResponseServerError {
+            status_code: 404, ..
+        }) => Err(OgError::msg(
+            ErrorKind::Usage,
+            format!(
+                "Container {} doesn't exist, create it with: og sql start --name {}",
+                instance.container_name(),
+                instance.name
+            ),
+        ))?,
+        Err(error) => return Err(error.into()),
+    };
+
+    Ok(host_port(&inspect).unwrap_or(instance.port))
+}
+
+/// A recreated container stays on the port it was published on, unless another one was asked for
+async fn keep_published_port(docker: &Docker, instance: &mut SqlInstance) {
+    if let Ok(inspect) = docker
+        .inspect_container(&instance.container_name(), None)
+        .await
+    {
+        if let Some(port) = host_port(&inspect) {
+            instance.port = port;
+        }
+    }
+}
+
+/// Asks before deleting the data volume, listing the databases if SQL Server is running
+async fn confirm_data_loss(
+    docker: Docker,
+    instance: &SqlInstance,
+    status: &ContainerStateStatusEnum,
+) -> Result<()> {
+    let databases = if *status == RUNNING {
+        user_databases(docker, instance)
+            .await
+            .map_err(|e| debug!("Could not list databases: {e}"))
+            .ok()
+    } else {
+        None
+    };
+
+    if !prompt::confirm(
+        &data_loss_prompt(&instance.volume_name(), databases.as_deref()),
+        false,
+    )? {
+        Err(OgError::msg(
+            ErrorKind::UserAbort,
+            format!("Volume {} was not deleted", instance.volume_name()),
+        ))?;
+    }
+
+    Ok(())
+}
+
+async fn user_databases(docker: Docker, instance: &SqlInstance) -> Result<Vec<String>> {
+    let sqlcmd = Sqlcmd::connect(docker, instance).await?;
+    let tables = sqlcmd
+        .query(
+            "SELECT name FROM sys.databases WHERE database_id > 4 ORDER BY name",
+            None,
+        )
+        .await?;
+
+    Ok(tables
+        .iter()
+        .flat_map(|t| &t.rows)
+        .filter_map(|r| r.first().cloned())
+        .collect())
+}
+
+fn data_loss_prompt(volume_name: &str, databases: Option<&[String]>) -> String {
+    match databases {
+        Some([]) => format!("Delete volume {volume_name}? It holds no user databases"),
+        Some(databases) => format!(
+            "Delete volume {volume_name} with the databases {}? They will be lost",
+            databases.join(", ")
+        ),
+        None => format!(
+            "Delete volume {volume_name}? All databases in it will be lost, \
+             start the container to see which"
+        ),
+    }
+}
+
+async fn start(
+    docker: Docker,
+    instance: &SqlInstance,
+    status: ContainerStateStatusEnum,
+) -> Result<()> {
+    if status == EMPTY {
+        info!(
+            "Container {} doesn't exist, container will be created and started...",
+            instance.container_name()
+        );
+        create_and_run_container(docker, instance).await?;
+        return Ok(());
+    }
+
+    if let Ok(port) = published_port(&docker, instance).await {
+        ports::check(
+            &docker,
+            port,
+            &instance.container_name(),
+            &format!(
+                "move the container with: og sql start --recreate --port {}",
+                port.saturating_add(1)
+            ),
+        )
+        .await?;
+    }
+    info!(
+        "Container {} exists but was stopped, container will restart...",
+        instance.container_name()
+    );
+    restart_container(docker, instance).await?;
+    Ok(())
+}
+
+/// Port and image are fixed once the container is created, a port chosen by `ports::choose` is kept
+async fn warn_on_changed_settings(
+    docker: Docker,
+    instance: &SqlInstance,
+    requested_port: Option<u16>,
+) -> Result<()> {
+    let inspect = docker
+        .inspect_container(&instance.container_name(), None)
+        .await?;
+
+    let image = inspect.config.as_ref().and_then(|c| c.image.as_deref());
+    if image.is_some_and(|i| i != instance.image) {
+        warn!(
+            "Container {} runs {}, not {}, apply it with: og sql start --recreate",
+            instance.container_name(),
+            image.unwrap_or_default(),
+            instance.image
+        );
+    }
+
+    let port = host_port(&inspect);
+    if let (Some(port), Some(requested)) = (port, requested_port) {
+        if port != requested {
+            warn!(
+                "Container {} is published on port {port}, not {requested}, apply it with: og sql start --recreate",
+                instance.container_name()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn stop(docker: Docker, instance: &SqlInstance) -> Result<()> {
+    let container_name = instance.container_name();
+    info!("Stopping container {}...", container_name);
+    docker.stop_container(&container_name, None).await?;
+    info!("Container {} stopped ", container_name);
+    Ok(())
+}
+
+async fn restart_container(docker: Docker, instance: &SqlInstance) -> Result<()> {
+    Ok(docker
+        .restart_container(
+            &instance.container_name(),
+            Some(RestartContainerOptions { t: 10 }),
+        )
+        .await?)
+}
+
+async fn create_and_run_container(docker: Docker, instance: &SqlInstance) -> Result<()> {
+    pull_image(&docker, &instance.image).await?;
+    run_container(docker, instance).await
+}
+
+async fn run_container(docker: Docker, instance: &SqlInstance) -> Result<()> {
+    let pwd = &get_config().sql_password;
+    let formatted_pwd = &format!("MSSQL_SA_PASSWORD={pwd}");
+    let env = vec![formatted_pwd.as_str(), "ACCEPT_EULA=Y"];
+    let container_name = instance.container_name();
+
+    let port_bindings = {
+        let mut map = HashMap::new();
+        map.insert(
+            format!("{}/tcp", CONTAINER_PORT),
+            Some(vec![PortBinding {
+                host_ip: Some("0.0.0.0".to_string()),
+                host_port: Some(instance.port.to_string()),
+            }]),
+        );
+        map
+    };
+
+    let options = Some(CreateContainerOptions {
+        name: container_name.as_str(),
+        platform: None,
+    });
+
+    let creation_config = Config {
+        image: Some(instance.image.as_str()),
+        env: Some(env),
+        labels: Some(instance.labels()),
+        healthcheck: Some(healthcheck()),
+        host_config: Some(HostConfig {
+            binds: Some(vec![format!(
+                "{}:/var/opt/mssql:rw",
+                instance.volume_name()
+            )]),
+            port_bindings: Some(port_bindings),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    let result = docker.create_container(options, creation_config).await?;
+
+    docker
+        .start_container(&container_name, None::<StartContainerOptions<String>>)
+        .await?;
+
+    info!("Container {} created and started", result.id);
+
+    Ok(())
+}
+
+async fn get_container_status(
+    docker: Docker,
+    instance: &SqlInstance,
+) -> Result<ContainerStateStatusEnum> {
+    let inspect = docker
+        .inspect_container(&instance.container_name(), None)
+        .await;
+    match inspect {
+        Ok(i) => Ok(i.state.and_then(|s| s.status).unwrap_or(EMPTY)),
+        Err(e) => {
+            if e.to_string().contains("404") {
+                Ok(EMPTY)
+            } else {
+                Err(eyre::eyre!(e))
+            }
+        }
+    }
+}
+
+async fn init_docker() -> Result<Docker> {
+    container_runtime::connect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn data_loss_prompt_lists_databases() {
+        assert_eq!(
+            data_loss_prompt("sql-data", Some(&["Erp".into(), "Shop".into()])),
+            "Delete volume sql-data with the databases Erp, Shop? They will be lost"
+        );
+        assert!(data_loss_prompt("sql-data", Some(&[])).contains("no user databases"));
+        assert!(data_loss_prompt("sql-data-erp", None).contains("All databases"));
+    }
+}
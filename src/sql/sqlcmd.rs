@@ -0,0 +1,608 @@
+use bollard::{
+    container::{
+        AttachContainerOptions, Config, CreateContainerOptions, DownloadFromContainerOptions,
+        LogOutput, RemoveContainerOptions, StartContainerOptions, UploadToContainerOptions,
+        WaitContainerOptions,
+    },
+    errors::Error as DockerError,
+    exec::{CreateExecOptions, StartExecResults},
+    image::CreateImageOptions,
+    models::{HealthConfig, HostConfig},
+    Docker,
+};
+use eyre::Result;
+use futures_util::{Stream, StreamExt, TryStreamExt};
+use log::{debug, info};
+use std::pin::Pin;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_util::io::StreamReader;
+
+use super::{
+    archive,
+    instance::{SqlInstance, SA_USER, X86_64_IMAGE},
+};
+use crate::{
+    common_docker::split_image,
+    error::{ErrorKind, OgError},
+    get_config,
+    output::Table,
+};
+
+/// Locations of sqlcmd in the mssql/server images, tools18 requires `-C` for the self-signed certificate
+const SQLCMD_PATHS: [&str; 2] = [
+    "/opt/mssql-tools18/bin/sqlcmd",
+    "/opt/mssql-tools/bin/sqlcmd",
+];
+/// Azure SQL Edge ships without sqlcmd, so it runs in the pinned SQL Server image next to the container
+const TOOLS_IMAGE: &str = X86_64_IMAGE;
+const TOOLS_SQLCMD: &str = "/opt/mssql-tools18/bin/sqlcmd";
+/// sqlcmd only ships for x86_64, other hosts run it emulated
+const TOOLS_PLATFORM: &str = "linux/amd64";
+const COLUMN_SEPARATOR: char = '\u{1f}';
+/// The archive API takes a whole archive per request, larger files are uploaded in parts
+const UPLOAD_PART_SIZE: usize = 64 * 1024 * 1024;
+
+const SECOND: i64 = 1_000_000_000;
+
+type OutputStream = Pin<Box<dyn Stream<Item = Result<LogOutput, DockerError>> + Send>>;
+type InputStream = Pin<Box<dyn AsyncWrite + Send>>;
+
+#[derive(Debug)]
+pub struct SqlcmdOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit_code: i64,
+}
+
+/// Runs sqlcmd as SA against a running instance
+pub struct Sqlcmd {
+    docker: Docker,
+    container_name: String,
+    /// Path of sqlcmd inside the instance's container, if it has one
+    container_sqlcmd: Option<String>,
+}
+
+impl Sqlcmd {
+    pub async fn connect(docker: Docker, instance: &SqlInstance) -> Result<Self> {
+        let container_name = instance.container_name();
+        let find_sqlcmd = format!(
+            "for p in {}; do if [ -x \"$p\" ]; then echo \"$p\"; exit 0; fi; done; exit 1",
+            SQLCMD_PATHS.join(" ")
+        );
+        let output = exec(
+            &docker,
+            &container_name,
+            vec!["sh".into(), "-c".into(), find_sqlcmd],
+            Vec::new(),
+            None,
+        )
+        .await?;
+
+        let container_sqlcmd = (output.exit_code == 0).then(|| output.stdout.trim().to_string());
+        match &container_sqlcmd {
+            Some(path) => debug!("Using {path} in container {container_name}"),
+            None => debug!("Container {container_name} has no sqlcmd, using {TOOLS_IMAGE}"),
+        }
+
+        Ok(Self {
+            docker,
+            container_name,
+            container_sqlcmd,
+        })
+    }
+
+    /// Runs a script, batches are separated by `GO` lines like in sqlcmd files
+    pub async fn run(&self, script: &str, database: Option<&str>) -> Result<SqlcmdOutput> {
+        let sqlcmd = self.container_sqlcmd.as_deref().unwrap_or(TOOLS_SQLCMD);
+        let mut cmd: Vec<String> = [
+            sqlcmd,
+            "-S",
+            "localhost",
+            "-U",
+            SA_USER,
+            "-b",
+            "-r1",
+            "-w",
+            "65535",
+            "-s",
+        ]
+        .iter()
+        .map(|a| a.to_string())
+        .collect();
+        cmd.push(COLUMN_SEPARATOR.to_string());
+        if let Some(database) = database {
+            cmd.extend(["-d".to_string(), database.to_string()]);
+        }
+        if sqlcmd.contains("tools18") {
+            cmd.push("-C".into());
+        }
+        // Keeps the password out of the process list
+        let env = vec![format!("SQLCMDPASSWORD={}", get_config().sql_password)];
+        let script = format!("SET NOCOUNT ON\nGO\n{script}\nGO\n");
+
+        match self.container_sqlcmd {
+            Some(_) => exec(&self.docker, &self.container_name, cmd, env, Some(&script)).await,
+            None => self.run_in_tools_container(cmd, env, &script).await,
+        }
+    }
+
+    /// Runs the SQL and returns its result sets, messages like `PRINT` output are logged
+    pub async fn query(&self, sql: &str, database: Option<&str>) -> Result<Vec<Table>> {
+        let output = self.run(sql, database).await?;
+        let (tables, messages) = parse_output(&output.stdout);
+        messages.iter().for_each(|m| info!("{m}"));
+
+        if output.exit_code != 0 {
+            Err(OgError::msg(
+                ErrorKind::ExternalTool,
+                format!(
+                    "sqlcmd failed with exit code {}: {}",
+                    output.exit_code,
+                    output.stderr.trim()
+                ),
+            ))?;
+        }
+
+        Ok(tables)
+    }
+
+    /// Runs a command in the instance's container, e.g. to manage backup files
+    pub async fn run_in_container(&self, cmd: &[&str]) -> Result<()> {
+        let output = exec(
+            &self.docker,
+            &self.container_name,
+            cmd.iter().map(|c| c.to_string()).collect(),
+            Vec::new(),
+            None,
+        )
+        .await?;
+
+        if output.exit_code != 0 {
+            Err(OgError::msg(
+                ErrorKind::Docker,
+                format!(
+                    "{} failed in container {}: {}",
+                    cmd.join(" "),
+                    self.container_name,
+                    output.stderr.trim()
+                ),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Copies `source` into a file in the container, in parts so backups never have to fit in memory
+    pub async fn upload(
+        &self,
+        source: &mut (impl AsyncRead + Unpin),
+        container_file: &str,
+    ) -> Result<()> {
+        let (dir, name) = container_file
+            .rsplit_once('/')
+            .unwrap_or((".", container_file));
+        let mut parts = Vec::new();
+        let mut content = Vec::with_capacity(UPLOAD_PART_SIZE);
+
+        let uploaded: Result<()> = async {
+            loop {
+                content.clear();
+                (&mut *source)
+                    .take(UPLOAD_PART_SIZE as u64)
+                    .read_to_end(&mut content)
+                    .await?;
+                let last = content.len() < UPLOAD_PART_SIZE;
+                if last && parts.is_empty() {
+                    return self.upload_archive(dir, name, &content).await;
+                }
+
+                let part = format!("{name}.part{:05}", parts.len());
+                self.upload_archive(dir, &part, &content).await?;
+                parts.push(format!("{dir}/{part}"));
+                if last {
+                    break;
+                }
+            }
+
+            let join = ["sh", "-c", "out=$1; shift; cat \"$@\" > \"$out\"", "sh"];
+            let cmd: Vec<&str> = join
+                .into_iter()
+                .chain([container_file])
+                .chain(parts.iter().map(String::as_str))
+                .collect();
+            self.run_in_container(&cmd).await
+        }
+        .await;
+
+        if !parts.is_empty() {
+            let remove: Vec<&str> = ["rm", "-f"]
+                .into_iter()
+                .chain(parts.iter().map(String::as_str))
+                .collect();
+            self.run_in_container(&remove).await?;
+        }
+        uploaded
+    }
+
+    async fn upload_archive(&self, dir: &str, name: &str, content: &[u8]) -> Result<()> {
+        let archive = archive::single_file(name, content)?;
+        self.docker
+            .upload_to_container(
+                &self.container_name,
+                Some(UploadToContainerOptions {
+                    path: dir,
+                    ..Default::default()
+                }),
+                archive.into(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Streams a file out of the container into `destination`
+    pub async fn download(
+        &self,
+        container_file: &str,
+        destination: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<()> {
+        let stream = self
+            .docker
+            .download_from_container(
+                &self.container_name,
+                Some(DownloadFromContainerOptions {
+                    path: container_file,
+                }),
+            )
+            .map_err(io::Error::other);
+
+        archive::extract_file(&mut StreamReader::new(stream), destination).await
+    }
+
+    async fn run_in_tools_container(
+        &self,
+        cmd: Vec<String>,
+        env: Vec<String>,
+        stdin: &str,
+    ) -> Result<SqlcmdOutput> {
+        if self.docker.inspect_image(TOOLS_IMAGE).await.is_err() {
+            info!("Pulling {TOOLS_IMAGE} to run sqlcmd...");
+            let (repository, tag) = split_image(TOOLS_IMAGE);
+            self.docker
+                .create_image(
+                    Some(CreateImageOptions {
+                        from_image: repository,
+                        tag,
+                        platform: TOOLS_PLATFORM,
+                        ..Default::default()
+                    }),
+                    None,
+                    None,
+                )
+                .try_collect::<Vec<_>>()
+                .await?;
+        }
+
+        let name = format!("{}-sqlcmd-{}", self.container_name, std::process::id());
+        self.docker
+            .create_container(
+                Some(CreateContainerOptions {
+                    name: name.as_str(),
+                    platform: Some(TOOLS_PLATFORM),
+                }),
+                Config {
+                    image: Some(TOOLS_IMAGE.to_string()),
+                    cmd: Some(cmd),
+                    env: Some(env),
+                    open_stdin: Some(true),
+                    stdin_once: Some(true),
+                    attach_stdin: Some(true),
+                    attach_stdout: Some(true),
+                    attach_stderr: Some(true),
+                    host_config: Some(HostConfig {
+                        // Shares the network namespace, so SQL Server is reachable on localhost
+                        network_mode: Some(format!("container:{}", self.container_name)),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+        let result = self.attach_and_wait(&name, stdin).await;
+        self.docker
+            .remove_container(
+                &name,
+                Some(RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                }),
+            )
+            .await?;
+
+        match result {
+            // Hosts without x86_64 emulation can't run the sqlcmd binary
+            Ok(output) if output.stderr.contains("exec format error") => Err(OgError::msg(
+                ErrorKind::Docker,
+                format!(
+                    "{} has no sqlcmd and the x86_64 image {TOOLS_IMAGE} can't run here, enable x86_64 \
+                     emulation (e.g. Rosetta in Docker Desktop) or use an image that ships sqlcmd",
+                    self.container_name
+                ),
+            ))?,
+            result => result,
+        }
+    }
+
+    async fn attach_and_wait(&self, name: &str, stdin: &str) -> Result<SqlcmdOutput> {
+        let attached = self
+            .docker
+            .attach_container(
+                name,
+                Some(AttachContainerOptions::<String> {
+                    stdin: Some(true),
+                    stdout: Some(true),
+                    stderr: Some(true),
+                    stream: Some(true),
+                    ..Default::default()
+                }),
+            )
+            .await?;
+        self.docker
+            .start_container(name, None::<StartContainerOptions<String>>)
+            .await?;
+
+        let (stdout, stderr) = communicate(attached.output, attached.input, Some(stdin)).await?;
+
+        let exit_code = match self
+            .docker
+            .wait_container(name, None::<WaitContainerOptions<String>>)
+            .try_collect::<Vec<_>>()
+            .await
+        {
+            Ok(responses) => responses.last().map(|r| r.status_code).unwrap_or_default(),
+            Err(DockerError::DockerContainerWaitError { code, .. }) => code,
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(SqlcmdOutput {
+            stdout,
+            stderr,
+            exit_code,
+        })
+    }
+}
+
+async fn exec(
+    docker: &Docker,
+    container_name: &str,
+    cmd: Vec<String>,
+    env: Vec<String>,
+    stdin: Option<&str>,
+) -> Result<SqlcmdOutput> {
+    let created = docker
+        .create_exec(
+            container_name,
+            CreateExecOptions {
+                attach_stdin: Some(stdin.is_some()),
+                attach_stdout: Some(true),
+                attach_stderr: Some(true),
+                cmd: Some(cmd),
+                env: Some(env),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+    let (stdout, stderr) = match docker.start_exec(&created.id, None).await? {
+        StartExecResults::Attached { output, input } => communicate(output, input, stdin).await?,
+        StartExecResults::Detached => (String::new(), String::new()),
+    };
+    let exit_code = docker
+        .inspect_exec(&created.id)
+        .await?
+        .exit_code
+        .unwrap_or_default();
+
+    Ok(SqlcmdOutput {
+        stdout,
+        stderr,
+        exit_code,
+    })
+}
+
+/// Writes stdin while collecting stdout and stderr, so a process with a full output pipe can't block the write
+async fn communicate(
+    output: OutputStream,
+    mut input: InputStream,
+    stdin: Option<&str>,
+) -> Result<(String, String)> {
+    let write = async {
+        if let Some(stdin) = stdin {
+            input.write_all(stdin.as_bytes()).await?;
+        }
+        input.shutdown().await.map_err(eyre::Report::from)
+    };
+    let (_, output) = tokio::try_join!(write, collect(output))?;
+
+    Ok(output)
+}
+
+/// Collects stdout and stderr until the process exits
+async fn collect(mut output: OutputStream) -> Result<(String, String)> {
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    while let Some(chunk) = output.next().await {
+        match chunk? {
+            LogOutput::StdErr { message } => stderr.push_str(&String::from_utf8_lossy(&message)),
+            LogOutput::StdOut { message } | LogOutput::Console { message } => {
+                stdout.push_str(&String::from_utf8_lossy(&message))
+            }
+            LogOutput::StdIn { .. } => {}
+        }
+    }
+
+    Ok((stdout, stderr))
+}
+
+/// Docker healthcheck logging in as SA, Azure SQL Edge has no sqlcmd and only gets a port check
+pub fn healthcheck() -> HealthConfig {
+    let script = format!(
+        "for p in {}; do if [ -x \"$p\" ]; then \
+         case $p in *tools18*) c=-C;; esac; \
+         exec \"$p\" -S localhost -U {SA_USER} -P \"$MSSQL_SA_PASSWORD\" $c -b -Q 'SELECT 1' -o /dev/null; \
+         fi; done; exec bash -c '</dev/tcp/127.0.0.1/1433'",
+        SQLCMD_PATHS.join(" ")
+    );
+
+    HealthConfig {
+        test: Some(vec!["CMD-SHELL".to_string(), script]),
+        interval: Some(10 * SECOND),
+        timeout: Some(5 * SECOND),
+        retries: Some(5),
+        start_period: Some(30 * SECOND),
+        start_interval: Some(2 * SECOND),
+    }
+}
+
+/// Quotes a name like a database for T-SQL, e.g. `[my db]`
+pub fn quote_name(name: &str) -> String {
+    format!("[{}]", name.replace(']', "]]"))
+}
+
+/// Quotes a T-SQL unicode string literal
+pub fn quote_string(value: &str) -> String {
+    format!("N'{}'", value.replace('\'', "''"))
+}
+
+/// Splits sqlcmd output into result sets (header, dashes, rows) and other messages.
+/// Values are padded to the column width, so only a truly empty line ends a result set
+fn parse_output(output: &str) -> (Vec<Table>, Vec<String>) {
+    let lines: Vec<&str> = output.lines().collect();
+    let mut tables = Vec::new();
+    let mut messages = Vec::new();
+
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some(widths) = lines
+            .get(i + 1)
+            .and_then(|separator| column_widths(lines[i], separator))
+        {
+            let columns = split_row(lines[i], &widths);
+            i += 2;
+            let mut rows = Vec::new();
+            while i < lines.len() && !lines[i].is_empty() {
+                rows.push(split_row(lines[i], &widths));
+                i += 1;
+            }
+            tables.push(Table { columns, rows });
+        } else {
+            if !lines[i].trim().is_empty() {
+                messages.push(lines[i].to_string());
+            }
+            i += 1;
+        }
+    }
+
+    (tables, messages)
+}
+
+/// Widths of the columns if `separator` is the dashes under the header, which is padded to the same width
+fn column_widths(header: &str, separator: &str) -> Option<Vec<usize>> {
+    let widths: Vec<usize> = separator
+        .split(COLUMN_SEPARATOR)
+        .map(|dashes| {
+            (!dashes.is_empty() && dashes.chars().all(|c| c == '-')).then(|| dashes.chars().count())
+        })
+        .collect::<Option<_>>()?;
+
+    (header.chars().count() == separator.chars().count()).then_some(widths)
+}
+
+/// Cuts a row at the column widths, values may contain the separator
+fn split_row(line: &str, widths: &[usize]) -> Vec<String> {
+    let mut chars = line.chars();
+    widths
+        .iter()
+        .map(|width| {
+            let value: String = chars.by_ref().take(*width).collect();
+            chars.next();
+            // Numbers are right-aligned
+            value.trim().to_string()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_result_sets_and_messages() {
+        let output = "name  \u{1f}database_id\n------\u{1f}-----------\nmaster\u{1f}          1\nerp   \u{1f}          5\n\nDone\n\nversion\n-------\n16.0   \n";
+
+        let (tables, messages) = parse_output(output);
+
+        assert_eq!(
+            tables,
+            vec![
+                Table {
+                    columns: vec!["name".into(), "database_id".into()],
+                    rows: vec![
+                        vec!["master".into(), "1".into()],
+                        vec!["erp".into(), "5".into()]
+                    ],
+                },
+                Table {
+                    columns: vec!["version".into()],
+                    rows: vec![vec!["16.0".into()]],
+                }
+            ]
+        );
+        assert_eq!(messages, vec!["Done".to_string()]);
+    }
+
+    #[test]
+    fn keeps_empty_values_in_single_columns() {
+        let output = "note\n----\n    \nabc \n\nDone\n";
+
+        let (tables, messages) = parse_output(output);
+
+        assert_eq!(
+            tables[0].rows,
+            vec![vec![String::new()], vec!["abc".into()]]
+        );
+        assert_eq!(messages, vec!["Done".to_string()]);
+    }
+
+    #[test]
+    fn reads_dashes_as_values() {
+        let output = "Changed database context to 'erp'.\n---\nnote \n-----\n---  \n\n";
+
+        let (tables, messages) = parse_output(output);
+
+        assert_eq!(messages, vec!["Changed database context to 'erp'.", "---"]);
+        assert_eq!(
+            tables,
+            vec![Table {
+                columns: vec!["note".into()],
+                rows: vec![vec!["---".into()]],
+            }]
+        );
+    }
+
+    #[test]
+    fn quotes_names_and_strings() {
+        assert_eq!(quote_name("erp]test"), "[erp]]test]");
+        assert_eq!(quote_string("O'Neil"), "N'O''Neil'");
+    }
+
+    #[test]
+    fn parses_empty_result_set() {
+        let (tables, messages) = parse_output("name\n----\n\n");
+
+        assert_eq!(tables[0].columns, vec!["name".to_string()]);
+        assert!(tables[0].rows.is_empty());
+        assert!(messages.is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+use bollard::{
+    container::InspectContainerOptions, errors::Error as DockerError,
+    models::ContainerInspectResponse, Docker,
+};
+use eyre::Result;
+use log::info;
+use serde_json::Value;
+
+use super::instance::{SqlInstance, CONTAINER_PORT, SA_USER};
+use crate::{
+    common_docker::{self, container_fields},
+    connection_string::{self, ConnectionFormat, Endpoint},
+    get_config, output,
+};
+
+/// Mount point of the data volume inside the container
+const DATA_DIR: &str = "/var/opt/mssql";
+
+pub async fn status(docker: &Docker, instance: &SqlInstance) -> Result<()> {
+    let container_name = instance.container_name();
+    let inspect = match docker
+        .inspect_container(&container_name, None::<InspectContainerOptions>)
+        .await
+    {
+        Ok(inspect) => inspect,
+        Err(DockerError::DockerResponseServerError {
+            status_code: 404, ..
+        }) => {
+            info!(
+                "Container {container_name} doesn't exist, create it with: og sql start --name {}",
+                instance.name
+            );
+            return Ok(());
+        }
+        Err(error) => return Err(error.into()),
+    };
+
+    let port = host_port(&inspect);
+    let mut record = vec![
+        ("name", Value::from(instance.name.as_str())),
+        ("container", Value::from(container_name)),
+    ];
+    record.extend(container_fields(docker, &inspect, CONTAINER_PORT, DATA_DIR).await?);
+    record.push((
+        "connection_string",
+        Value::from(port.map(|port| {
+            connection_string::sql_server(
+                ConnectionFormat::Ado,
+                &Endpoint {
+                    host: "localhost",
+                    port,
+                    user: SA_USER,
+                    password: &get_config().sql_password,
+                    database: None,
+                },
+            )
+        })),
+    ));
+    output::print_record(&record);
+
+    Ok(())
+}
+
+/// The host port SQL Server is published on, also known while the container is stopped
+pub fn host_port(inspect: &ContainerInspectResponse) -> Option<u16> {
+    common_docker::host_port(inspect, CONTAINER_PORT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bollard::models::{HostConfig, PortBinding};
+    use std::collections::HashMap;
+
+    #[test]
+    fn finds_host_port() {
+        let inspect = ContainerInspectResponse {
+            host_config: Some(HostConfig {
+                port_bindings: Some(HashMap::from([(
+                    "1433/tcp".to_string(),
+                    Some(vec![PortBinding {
+                        host_ip: Some("0.0.0.0".into()),
+                        host_port: Some("14330".into()),
+                    }]),
+                )])),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        assert_eq!(host_port(&inspect), Some(14330));
+        assert_eq!(host_port(&ContainerInspectResponse::default()), None);
+    }
+}